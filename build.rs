@@ -0,0 +1,26 @@
+//! Embeds the default network, which is `default.nnue` next to this file unless
+//! `DYSPROSIUM_EVALFILE` points to another one.
+
+use std::path::PathBuf;
+
+fn main() {
+    println!("cargo::rustc-check-cfg=cfg(embedded_net)");
+    println!("cargo::rerun-if-env-changed=DYSPROSIUM_EVALFILE");
+
+    let overridden = std::env::var_os("DYSPROSIUM_EVALFILE");
+    let path = PathBuf::from(std::env::var_os("CARGO_MANIFEST_DIR").unwrap())
+        .join(overridden.clone().unwrap_or_else(|| "default.nnue".into()));
+    println!("cargo::rerun-if-changed={}", path.display());
+
+    if path.is_file() {
+        println!("cargo::rustc-cfg=embedded_net");
+        println!("cargo::rustc-env=DYSPROSIUM_EVALFILE={}", path.display());
+    } else if overridden.is_some() {
+        panic!("`DYSPROSIUM_EVALFILE` is set to `{}`, which is not a file", path.display());
+    } else {
+        println!(
+            "cargo::warning=there is no default network at `{}`, the handcrafted evaluation is used unless `EvalFile` is set",
+            path.display(),
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use std::io::Read;
 
-use chess::{ChessMove, Color, Piece, Square};
+use chess::{ChessMove, Color};
 // use reqwest::{header, Client, Request, Response, Result as ReqResure
 use serde::Deserialize;
 use serde_json::from_str;
@@ -238,24 +238,6 @@ impl<R: Read> JsonStreamIter<R> {
     }
 }
 
-pub fn move_from_uci(m: &str) -> ChessMove {
-    let src = &m[0..2];
-    let src = Square::new(((src.as_bytes()[1] - b'1') << 3) + (src.as_bytes()[0] - b'a'));
-
-    let dst = &m[2..4];
-    let dst = Square::new(((dst.as_bytes()[1] - b'1') << 3) + (dst.as_bytes()[0] - b'a'));
-
-    let piece = m.as_bytes().get(4).and_then(|p| match p {
-        b'n' => Some(Piece::Knight),
-        b'b' => Some(Piece::Bishop),
-        b'q' => Some(Piece::Queen),
-        b'r' => Some(Piece::Rook),
-        _ => None,
-    });
-
-    ChessMove::new(src, dst, piece)
-}
-
 fn success(status: u16) -> bool {
     (200..=299).contains(&status)
 }
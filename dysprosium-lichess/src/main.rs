@@ -2,9 +2,9 @@
 
 use core::str::FromStr;
 use std::sync::{atomic::*, Arc};
-use api::{move_from_uci, Challenge, Direction, Event, GameEvent, GameState, LichessApi, Player, Speed, Variant};
-use chess::{Board, Color};
-use dysprosium::Game;
+use api::{Challenge, Direction, Event, GameEvent, GameState, LichessApi, Player, Speed, Variant};
+use chess::Color;
+use dysprosium::{Game, book::{Book, BookSelection}};
 
 mod api;
 mod log;
@@ -14,15 +14,22 @@ const EXCEPTION_USERS: &[&str] = &["funnsam"];
 const ACCEPT_RATED: bool = false;
 
 const THREADS_PER_GAME: usize = 2;
+/// Downloaded by `get_book.sh`, the bot plays without a book if it is missing
+const BOOK_FILE: &str = "Perfect2023.bin";
 
 pub struct LichessClient {
     api: LichessApi,
     pub active_games: AtomicUsize,
+    book: Option<Book>,
 }
 
 impl LichessClient {
     pub fn new(api: LichessApi) -> Self {
-        Self { api, active_games: AtomicUsize::new(0) }
+        let book = Book::open(BOOK_FILE)
+            .inspect_err(|err| warn!("can't open book `{BOOK_FILE}`: {err}"))
+            .ok();
+
+        Self { api, active_games: AtomicUsize::new(0), book }
     }
 
     pub fn listen(self: Arc<Self>) {
@@ -66,9 +73,15 @@ impl LichessClient {
         self.api.listen_game(&game_id, |event| match event {
             GameEvent::GameFull { initial_fen, state } => {
                 let mut game = engine.game.write();
-                *game = Game::new(Board::from_str(initial_fen).unwrap_or_default());
+                *game = Game::from_str(initial_fen).unwrap_or_default();
                 for m in state.moves.split_whitespace() {
-                    *game = game.make_move(move_from_uci(m));
+                    match game.parse_uci(m) {
+                        Ok(m) => game.play_move(m),
+                        Err(err) => {
+                            error!("{err} (id: `{game_id}`)");
+                            break;
+                        },
+                    }
                 }
 
                 if game.board().side_to_move() == color {
@@ -79,7 +92,10 @@ impl LichessClient {
             GameEvent::GameState { state } => {
                 let mut game = engine.game.write();
                 if let Some(m) = state.moves.split_whitespace().last() {
-                    *game = game.make_move(move_from_uci(m));
+                    match game.parse_uci(m) {
+                        Ok(m) => game.play_move(m),
+                        Err(err) => error!("{err} (id: `{game_id}`)"),
+                    }
                 }
 
                 if game.board().side_to_move() == color {
@@ -105,17 +121,23 @@ impl LichessClient {
             },
         });
 
+        let book_move = self.book.as_ref()
+            .and_then(|book| book.pick(&engine.game.read(), BookSelection::WeightedRandom));
+        if let Some(mov) = book_move {
+            info!("playing book move {mov} (id: `{game_id}`)");
+            self.api.send_move(game_id, mov);
+            return;
+        }
+
         let (next, _, _) = engine.best_move(|engine, (best, eval, depth)| {
             let nodes = engine.nodes();
             let time = engine.elapsed().as_secs_f64();
+            let pv = engine.find_pv(best, 20);
 
             info!(
                 "searched {nodes} nodes at {depth}-ply deep in {time:.2}s ({:.2} MN/s), PV: {} ({eval})",
                 nodes as f64 / time / 1_000_000.0,
-                engine.find_pv(best, 20).into_iter()
-                .map(|m| m.to_string())
-                .collect::<Vec<_>>()
-                .join(" "),
+                engine.game.read().san_line(&pv),
             );
             true
         });
@@ -0,0 +1,290 @@
+//! Texel tuner for the handcrafted evaluation. It fits the evaluation weights to positions
+//! labelled with `dysprosium-uci datagen` and writes them in the format of `src/eval/weights.rs`.
+//!
+//! ```text
+//! dysprosium-tune <data> [epochs N] [lr X] [lambda X] [output PATH]
+//! ```
+
+use std::{fs::File, io::{BufRead, BufReader, Write}, str::FromStr, time::Instant};
+use chess::Color;
+use dysprosium::{Game, eval::{self, NUM_WEIGHTS, TERMS, Trace, WEIGHTS}};
+
+struct Options {
+    data: String,
+    epochs: usize,
+    /// Step size of Adam, which is roughly how far a weight moves in an epoch
+    learning_rate: f64,
+    /// How much the game result is trusted over the search score, 1 only learns from results
+    lambda: f64,
+    output: String,
+}
+
+/// A position reduced to what the evaluation sees of it
+struct Entry {
+    /// Amount of times every used weight is used, white's minus black's
+    coeffs: Vec<(u16, i16)>,
+    /// Weight of the midgame score, 1 is midgame and 0 is endgame
+    phase: f64,
+    /// Endgame scale of the position, 1 leaves the endgame score as it is
+    scale: f64,
+    /// Mop up score from white's point of view, which isn't tuned
+    mop_up: f64,
+    /// Game result from white's point of view
+    result: f64,
+    /// Search score from white's point of view
+    score: f64,
+}
+
+/// Counts weight uses for [`Entry::coeffs`], and keeps the endgame scale and mop up
+struct Coefficients([i16; NUM_WEIGHTS], i16, i16);
+
+impl Trace for Coefficients {
+    fn add(&mut self, index: usize, color: Color, count: i16) {
+        self.0[index] += if color == Color::White { count } else { -count };
+    }
+
+    fn endgame(&mut self, scale: i16, mop_up: i16) {
+        (self.1, self.2) = (scale, mop_up);
+    }
+}
+
+fn main() {
+    let Some(options) = parse_args() else {
+        println!("usage: dysprosium-tune <data> [epochs N] [lr X] [lambda X] [output PATH]");
+        return;
+    };
+
+    let start = Instant::now();
+    let entries = match load(&options.data) {
+        Ok(entries) => entries,
+        Err(err) => {
+            println!("can't read `{}`: {err}", options.data);
+            return;
+        },
+    };
+    println!("loaded {} positions in {:.2}s", entries.len(), start.elapsed().as_secs_f64());
+    if entries.is_empty() { return };
+
+    let mut weights = WEIGHTS.iter().map(|w| [w.0 as f64, w.1 as f64]).collect::<Vec<_>>();
+    let k = find_k(&entries, &weights, options.lambda);
+    println!("k = {k:.6}, error = {:.6}", error(&entries, &weights, k, options.lambda));
+
+    // Adam
+    const BETA1: f64 = 0.9;
+    const BETA2: f64 = 0.999;
+    const EPSILON: f64 = 1e-8;
+    let mut m = vec![[0.0; 2]; NUM_WEIGHTS];
+    let mut v = vec![[0.0; 2]; NUM_WEIGHTS];
+
+    for epoch in 1..=options.epochs {
+        let grad = gradient(&entries, &weights, k, options.lambda);
+
+        for i in 0..NUM_WEIGHTS {
+            for j in 0..2 {
+                m[i][j] = BETA1 * m[i][j] + (1.0 - BETA1) * grad[i][j];
+                v[i][j] = BETA2 * v[i][j] + (1.0 - BETA2) * grad[i][j] * grad[i][j];
+                let m_hat = m[i][j] / (1.0 - BETA1.powi(epoch as i32));
+                let v_hat = v[i][j] / (1.0 - BETA2.powi(epoch as i32));
+                weights[i][j] -= options.learning_rate * m_hat / (v_hat.sqrt() + EPSILON);
+            }
+        }
+
+        if epoch % 50 == 0 || epoch == options.epochs {
+            println!(
+                "epoch {epoch}, error = {:.6}, {:.2}s",
+                error(&entries, &weights, k, options.lambda),
+                start.elapsed().as_secs_f64(),
+            );
+
+            // keep the progress so far in case tuning is stopped
+            if let Err(err) = std::fs::write(&options.output, format_weights(&weights)) {
+                println!("can't write to `{}`: {err}", options.output);
+                return;
+            }
+        }
+    }
+
+    println!("weights written to `{}`", options.output);
+}
+
+fn parse_args() -> Option<Options> {
+    let mut args = std::env::args().skip(1);
+    let mut options = Options {
+        data: args.next()?,
+        epochs: 1000,
+        learning_rate: 1.0,
+        lambda: 1.0,
+        output: "weights.rs".to_string(),
+    };
+
+    while let Some(arg) = args.next() {
+        let value = args.next()?;
+        match arg.as_str() {
+            "epochs" => options.epochs = value.parse().ok()?,
+            "lr" => options.learning_rate = value.parse().ok()?,
+            "lambda" => options.lambda = value.parse().ok().filter(|l| (0.0..=1.0).contains(l))?,
+            "output" => options.output = value,
+            _ => return None,
+        }
+    }
+
+    Some(options)
+}
+
+/// Read `FEN | score | WDL` lines, skipping the ones that can't be parsed
+fn load(path: &str) -> std::io::Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+
+    for (n, l) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let l = l?;
+        let mut fields = l.split('|').map(str::trim);
+
+        let (Some(fen), Some(score), Some(result)) = (fields.next(), fields.next(), fields.next()) else {
+            println!("line {}: expected `FEN | score | WDL`", n + 1);
+            continue;
+        };
+        let (Ok(game), Ok(score), Ok(result)) = (Game::from_str(fen), score.parse::<f64>(), result.parse::<f64>()) else {
+            println!("line {}: invalid position `{l}`", n + 1);
+            continue;
+        };
+
+        let mut coeffs = Coefficients([0; NUM_WEIGHTS], eval::SCALE_NORMAL, 0);
+        eval::evaluate_traced(game.board(), &mut coeffs);
+
+        entries.push(Entry {
+            coeffs: coeffs.0.iter()
+                .enumerate()
+                .filter(|(_, c)| **c != 0)
+                .map(|(i, c)| (i as u16, *c))
+                .collect(),
+            phase: eval::game_phase(game.board()) as f64 / 24.0,
+            scale: coeffs.1 as f64 / eval::SCALE_NORMAL as f64,
+            mop_up: coeffs.2 as f64,
+            result,
+            score,
+        });
+    }
+
+    Ok(entries)
+}
+
+#[inline]
+fn sigmoid(k: f64, eval: f64) -> f64 {
+    1.0 / (1.0 + (-k * eval).exp())
+}
+
+impl Entry {
+    /// Evaluation from white's point of view with the weights
+    #[inline]
+    fn evaluate(&self, weights: &[[f64; 2]]) -> f64 {
+        let (mut mg, mut eg) = (0.0, 0.0);
+        for (i, c) in self.coeffs.iter() {
+            mg += weights[*i as usize][0] * *c as f64;
+            eg += weights[*i as usize][1] * *c as f64;
+        }
+
+        mg * self.phase + eg * self.scale * (1.0 - self.phase) + self.mop_up
+    }
+
+    #[inline]
+    fn target(&self, k: f64, lambda: f64) -> f64 {
+        lambda * self.result + (1.0 - lambda) * sigmoid(k, self.score)
+    }
+}
+
+/// Run `f` on a chunk of the entries per thread
+fn parallel<R: Send, F: Fn(&[Entry]) -> R + Sync>(entries: &[Entry], f: F) -> Vec<R> {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = entries.len().div_ceil(threads).max(1);
+    let f = &f;
+
+    std::thread::scope(|s| {
+        let handles = entries.chunks(chunk_size)
+            .map(|chunk| s.spawn(move || f(chunk)))
+            .collect::<Vec<_>>();
+
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    })
+}
+
+/// Mean squared error of the predicted results
+fn error(entries: &[Entry], weights: &[[f64; 2]], k: f64, lambda: f64) -> f64 {
+    let sum = parallel(entries, |chunk| chunk.iter()
+        .map(|e| (sigmoid(k, e.evaluate(weights)) - e.target(k, lambda)).powi(2))
+        .sum::<f64>()
+    ).into_iter().sum::<f64>();
+
+    sum / entries.len() as f64
+}
+
+/// Gradient of [`error`] with respect to every weight
+fn gradient(entries: &[Entry], weights: &[[f64; 2]], k: f64, lambda: f64) -> Vec<[f64; 2]> {
+    let partials = parallel(entries, |chunk| {
+        let mut grad = vec![[0.0; 2]; NUM_WEIGHTS];
+
+        for e in chunk {
+            let p = sigmoid(k, e.evaluate(weights));
+            let g = 2.0 * (p - e.target(k, lambda)) * p * (1.0 - p) * k;
+
+            for (i, c) in e.coeffs.iter() {
+                grad[*i as usize][0] += g * *c as f64 * e.phase;
+                grad[*i as usize][1] += g * *c as f64 * e.scale * (1.0 - e.phase);
+            }
+        }
+
+        grad
+    });
+
+    let mut grad = vec![[0.0; 2]; NUM_WEIGHTS];
+    for partial in partials {
+        for (g, p) in grad.iter_mut().zip(partial.iter()) {
+            g[0] += p[0] / entries.len() as f64;
+            g[1] += p[1] / entries.len() as f64;
+        }
+    }
+
+    grad
+}
+
+/// Find the scale of the sigmoid that fits the current weights best with a ternary search
+fn find_k(entries: &[Entry], weights: &[[f64; 2]], lambda: f64) -> f64 {
+    let (mut lo, mut hi) = (0.0, 0.1);
+
+    for _ in 0..50 {
+        let a = lo + (hi - lo) / 3.0;
+        let b = hi - (hi - lo) / 3.0;
+
+        if error(entries, weights, a, lambda) < error(entries, weights, b, lambda) {
+            hi = b;
+        } else {
+            lo = a;
+        }
+    }
+
+    (lo + hi) / 2.0
+}
+
+/// Write the weights as `src/eval/weights.rs`
+fn format_weights(weights: &[[f64; 2]]) -> String {
+    let mut out = Vec::new();
+    writeln!(out, "//! Evaluation weights, indexed by the constants in [`super`]. This file is written by").unwrap();
+    writeln!(out, "//! `dysprosium-tune`.").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "use super::{{NUM_WEIGHTS, S}};").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "pub const WEIGHTS: [S; NUM_WEIGHTS] = [").unwrap();
+
+    for (name, start, len) in TERMS {
+        writeln!(out, "    // {name}").unwrap();
+
+        for row in weights[*start..*start + *len].chunks(8) {
+            let row = row.iter()
+                .map(|w| format!("S({:>4}, {:>4}),", w[0].round() as i16, w[1].round() as i16))
+                .collect::<Vec<_>>();
+            writeln!(out, "    {}", row.join(" ")).unwrap();
+        }
+    }
+
+    writeln!(out, "];").unwrap();
+    String::from_utf8(out).unwrap()
+}
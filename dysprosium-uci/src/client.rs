@@ -1,23 +1,26 @@
+use std::sync::Arc;
 use crate::*;
 
-use dysprosium::Engine;
+use dysprosium::{Engine, SearchSignals, book::{Book, BookSelection}, nnue, syzygy::Tablebase};
+use crate::options::OptionValue;
 
 fn print_uci_info() {
     println!("id name dysprosium v{VERSION}");
     println!("id author funnsam");
-    println!(
-        "option name Hash type spin default {DEFAULT_HASH_SIZE_MB} min 1 max {}",
-        usize::MAX,
-    );
-    println!(
-        "option name Threads type spin default {DEFAULT_THREADS} min 1 max {}",
-        usize::MAX - 1,
-    );
+
+    for option in options::OPTIONS {
+        println!("{option}");
+    }
 }
 
 pub struct State {
     engine: Engine,
+    signals: Arc<SearchSignals>,
+    threads: usize,
     debug_mode: bool,
+    book: Option<Book>,
+    own_book: bool,
+    book_selection: BookSelection,
 }
 
 impl State {
@@ -26,73 +29,232 @@ impl State {
         engine.start_smp(DEFAULT_THREADS - 1);
 
         Self {
+            signals: engine.signals(),
             engine,
+            threads: DEFAULT_THREADS,
             debug_mode: false,
+            book: None,
+            own_book: false,
+            book_selection: BookSelection::WeightedRandom,
         }
     }
 
+    pub fn signals(&self) -> Arc<SearchSignals> {
+        Arc::clone(&self.signals)
+    }
+
     pub fn handle_command<'a>(&mut self, command: Option<uci::UciCommand<'a>>) {
         match command {
             Some(uci::UciCommand::Uci) => {
                 print_uci_info();
                 println!("uciok");
             },
-            Some(uci::UciCommand::SetOption(name, value)) => match name.to_ascii_lowercase().as_str() {
-                "hash" => self.engine.resize_hash(value.unwrap().parse::<usize>().unwrap() * MB),
-                "threads" => {
-                    self.engine.kill_smp();
-                    self.engine.start_smp(value.unwrap().parse::<usize>().unwrap() - 1);
-                },
-                _ => println!("info string got invalid setoption"),
+            Some(uci::UciCommand::SetOption(name, value)) => {
+                let option = options::UciOption::find(&name).and_then(|o| Ok((o.name, o.parse(value)?)));
+
+                match option {
+                    Ok(("Hash", OptionValue::Spin(mb))) => if let Err(err) = self.engine.resize_hash(mb as usize * MB) {
+                        println!("info string can't allocate {mb} MB of hash, keeping the old size: {err}");
+                    },
+                    Ok(("Threads", OptionValue::Spin(threads))) => {
+                        self.threads = threads as usize;
+                        self.engine.kill_smp();
+                        self.engine.start_smp(self.threads - 1);
+                    },
+                    Ok(("Clear Hash", _)) => self.engine.clear_hash(),
+                    Ok(("Ponder", _)) => {},
+                    Ok(("MultiPV", OptionValue::Spin(lines))) => self.engine.set_multi_pv(lines as usize),
+                    Ok(("OwnBook", OptionValue::Check(own_book))) => self.own_book = own_book,
+                    Ok(("BookFile", OptionValue::String(""))) => self.book = None,
+                    Ok(("BookFile", OptionValue::String(path))) => match Book::open(path) {
+                        Ok(book) => self.book = Some(book),
+                        Err(err) => println!("info string can't open book `{path}`: {err}"),
+                    },
+                    Ok(("BookBestMove", OptionValue::Check(best))) => {
+                        self.book_selection = if best { BookSelection::Best } else { BookSelection::WeightedRandom };
+                    },
+                    Ok(("EvalFile", OptionValue::String(""))) => nnue::set_network(nnue::embedded_network()),
+                    Ok(("EvalFile", OptionValue::String(path))) => match nnue::Network::load(path) {
+                        Ok(net) => nnue::set_network(Some(net)),
+                        Err(err) => println!("info string can't load network `{path}`: {err}"),
+                    },
+                    Ok(("SyzygyPath", OptionValue::String(""))) => self.engine.set_tablebase(None),
+                    Ok(("SyzygyPath", OptionValue::String(path))) => match Tablebase::open(path) {
+                        Ok(tb) => {
+                            println!("info string loaded up to {} piece tablebases", tb.max_pieces());
+                            self.engine.set_tablebase(Some(tb));
+                        },
+                        Err(err) => println!("info string can't load tablebases from `{path}`: {err:?}"),
+                    },
+                    Ok((name, _)) => println!("info string option `{name}` is not supported"),
+                    Err(err) => println!("info string {err}"),
+                }
             },
             Some(uci::UciCommand::Debug(d)) => self.debug_mode = d,
             Some(uci::UciCommand::IsReady) => println!("readyok"),
             Some(uci::UciCommand::Quit) => std::process::exit(0),
             Some(uci::UciCommand::UciNewGame) => {},
             Some(uci::UciCommand::Position { mut position, moves }) => {
-                for m in moves {
-                    position = position.make_move(m);
-                }
+                let result = moves.into_iter().try_for_each(|m| {
+                    position.play_move(position.parse_uci(m)?);
+                    Ok(())
+                });
 
-                *self.engine.game.write() = position;
+                match result {
+                    Ok(()) => *self.engine.game.write() = position,
+                    Err(err) => println!("info string {err}, position ignored"),
+                }
             },
             Some(uci::UciCommand::Move(m)) => {
-                *self.engine.game.write() = self.engine.game.read().make_move(m)
+                let mut game = self.engine.game.write();
+                match game.parse_move(m) {
+                    Ok(mov) => game.play_move(mov),
+                    Err(err) => println!("info string {err}"),
+                }
             },
-            Some(uci::UciCommand::Go { depth: target_depth, movetime, wtime, btime, movestogo }) => {
+            Some(uci::UciCommand::Go {
+                depth: target_depth,
+                movetime,
+                wtime,
+                btime,
+                movestogo,
+                infinite,
+                ponder,
+                nodes,
+                mate,
+                searchmoves,
+            }) => {
+                if self.own_book && !infinite && !ponder && searchmoves.is_empty() {
+                    let book_move = self.book.as_ref()
+                        .and_then(|book| book.pick(&self.engine.game.read(), self.book_selection));
+
+                    if let Some(mov) = book_move {
+                        self.signals.finish();
+                        println!("bestmove {mov}");
+                        return;
+                    }
+                }
+
                 let tc = if matches!(self.engine.game.read().board().side_to_move(), chess::Color::White) {
                     wtime
                 } else {
                     btime
                 };
-                if let Some(mt) = movetime {
+                if infinite {
+                    self.engine.allow_for(std::time::Duration::MAX);
+                } else if let Some(mt) = movetime {
                     self.engine.allow_for(mt);
                 } else if let Some(tc) = tc {
                     self.engine.time_control(movestogo, tc);
                 } else {
                     self.engine.allow_for(std::time::Duration::MAX);
                 }
+                self.engine.limit_nodes(nodes);
+                let searchmoves = {
+                    let game = self.engine.game.read();
+                    searchmoves.into_iter()
+                        .filter_map(|m| game.parse_uci(m).inspect_err(|err| println!("info string {err}")).ok())
+                        .collect()
+                };
+                self.engine.restrict_root_moves(searchmoves);
+
+                let mov = self.best_move(target_depth, mate);
+
+                // the best move can't be sent before a `stop` or `ponderhit` even if the search
+                // finished early
+                while !self.signals.stopped() && (infinite || (ponder && self.signals.pondering())) {
+                    std::thread::sleep(std::time::Duration::from_millis(1));
+                }
 
-                let mov = self.best_move(target_depth);
                 if self.debug_mode {
                     // NOTE: getting the amount of tt used can be expensive, so it is only counted
                     // if in debug mode
                     println!("info hashfull {}", 1000 * self.engine.tt_used() / self.engine.tt_size());
+                    println!("info string {:?}", self.engine.debug_stats());
+                }
+
+                self.signals.finish();
+                let pv = if mov != chess::ChessMove::default() { self.engine.find_pv(mov, 2) } else { vec![] };
+                if let Some(ponder) = pv.get(1) {
+                    println!("bestmove {mov} ponder {ponder}");
+                } else {
+                    println!("bestmove {mov}");
                 }
-                println!("bestmove {mov}");
             },
             Some(uci::UciCommand::D) => print!("{:#}", self.engine.game.read()),
-            Some(uci::UciCommand::Eval) => println!(
-                "{:#}Eval: {}",
-                self.engine.game.read(),
-                evaluate_static(self.engine.game.read().board()),
-            ),
-            Some(uci::UciCommand::Bench) => self.benchmark(),
+            Some(uci::UciCommand::Eval) => {
+                let game = self.engine.game.read().clone();
+                print!("{game:#}{}", eval::Breakdown::new(game.board()));
+                println!("Eval: {}", evaluate_static(&game));
+
+                // the breakdown is of the handcrafted evaluation, so the network gets its own line
+                if nnue::network().is_some() {
+                    println!("NNUE: {}", evaluate(&game));
+                }
+            },
+            Some(uci::UciCommand::Bench(depth)) => self.bench(depth.unwrap_or(bench::BENCH_DEPTH)),
+            Some(uci::UciCommand::SmpBench) => self.smp_benchmark(),
+            Some(uci::UciCommand::Datagen(options)) => datagen::datagen(options),
+            Some(uci::UciCommand::Perft(depth)) => {
+                let mut total = 0;
+                for (m, nodes) in perft_divide(&self.engine.game.read(), depth) {
+                    println!("{m}: {nodes}");
+                    total += nodes;
+                }
+
+                // `go perft` marks a search as running on the input thread
+                self.signals.finish();
+                println!();
+                println!("Nodes searched: {total}");
+            },
             None => {},
         }
     }
 
-    fn benchmark(&mut self) {
+    /// Search a fixed set of positions to a fixed depth on one thread, so that the node count
+    /// can be used as a signature of the search
+    fn bench(&mut self, depth: usize) {
+        self.engine.kill_smp();
+        self.engine.start_smp(0);
+        self.engine.limit_nodes(None);
+        self.engine.restrict_root_moves(Vec::new());
+
+        // the signature shouldn't depend on the options set before or on a `stop` sent while
+        // benchmarking
+        let game = self.engine.game.read().clone();
+        let multi_pv = self.engine.multi_pv();
+        let tablebase = self.engine.take_tablebase();
+        let net = nnue::replace_network(nnue::embedded_network().map(Arc::new));
+        self.engine.set_multi_pv(1);
+        self.engine.set_can_time_out(false);
+
+        let mut nodes = 0;
+        let mut time = std::time::Duration::ZERO;
+        for fen in bench::BENCH_POSITIONS {
+            *self.engine.game.write() = Game::from_str(fen).unwrap();
+            self.engine.clear_hash();
+
+            self.engine.allow_for(std::time::Duration::MAX);
+            self.engine.best_move(|_, (_, _, d)| d < depth);
+
+            nodes += self.engine.nodes();
+            time += self.engine.elapsed();
+        }
+
+        *self.engine.game.write() = game;
+        self.engine.set_multi_pv(multi_pv);
+        self.engine.set_tablebase(tablebase);
+        nnue::replace_network(net);
+        self.engine.set_can_time_out(true);
+
+        self.signals.finish();
+        self.engine.kill_smp();
+        self.engine.start_smp(self.threads - 1);
+
+        println!("{nodes} nodes {} nps", (nodes as f64 / time.as_secs_f64()) as u64);
+    }
+
+    fn smp_benchmark(&mut self) {
         let mut results = [0; 8];
 
         *self.engine.game.write() = Game::from_str("r5k1/5pp1/P1p1P2p/2R5/3r4/6PP/1P2R1K1/8 b - - 0 34").unwrap();
@@ -119,6 +281,9 @@ impl State {
             *rec = nodes / ITERS;
         }
 
+        self.signals.finish();
+        self.engine.kill_smp();
+        self.engine.start_smp(self.threads - 1);
         println!("{results:?}");
 
         let m = results[1] as f32 - results[0] as f32;
@@ -128,21 +293,31 @@ impl State {
         }
     }
 
-    fn best_move(&mut self, target_depth: Option<usize>) -> chess::ChessMove {
-        self.engine.best_move(|engine, (best, eval, depth)| {
+    fn best_move(&mut self, target_depth: Option<usize>, target_mate: Option<i16>) -> chess::ChessMove {
+        self.engine.best_move(|engine, (_, eval, depth)| {
             let time = engine.elapsed();
             let nodes = engine.nodes();
 
-            println!(
-                "info score {eval:#} depth {depth} nodes {nodes} time {} nps {} pv {}",
-                time.as_millis(),
-                (nodes as f64 / time.as_secs_f64()) as u64,
-                engine.find_pv(best, if self.debug_mode { 100 } else { 20 }).into_iter()
-                .map(|m| m.to_string())
-                .collect::<Vec<_>>()
-                .join(" "),
-            );
+            for (i, (best, eval)) in engine.pv_lines().into_iter().enumerate() {
+                println!(
+                    "info score {eval:#} depth {depth} multipv {} nodes {nodes} time {} nps {} tbhits {} pv {}",
+                    i + 1,
+                    time.as_millis(),
+                    (nodes as f64 / time.as_secs_f64()) as u64,
+                    engine.tb_hits(),
+                    engine.find_pv(best, if self.debug_mode { 100 } else { 20 }).into_iter()
+                    .map(|m| m.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                );
+
+                if self.debug_mode {
+                    let pv = engine.find_pv(best, 100);
+                    println!("info string multipv {} san {}", i + 1, engine.game.read().san_line(&pv));
+                }
+            }
             target_depth.map_or(true, |td| td > depth)
+                && target_mate.map_or(true, |tm| !eval.mate_in().is_some_and(|m| 0 < m && m <= tm))
         }).0
     }
 }
@@ -0,0 +1,151 @@
+use std::{fs::File, io::{BufWriter, Write}, sync::atomic::{AtomicUsize, Ordering}, time::{Duration, Instant}};
+use chess::{BoardStatus, Color, MoveGen};
+use dysprosium::{Engine, Eval, Game};
+use parking_lot::Mutex;
+use crate::MB;
+
+pub struct DatagenOptions<'a> {
+    pub threads: usize,
+    pub games: usize,
+    /// Node budget of every search
+    pub nodes: usize,
+    /// Amount of random moves played at the start of every game
+    pub random_plies: usize,
+    pub output: &'a str,
+}
+
+impl Default for DatagenOptions<'_> {
+    fn default() -> Self {
+        Self {
+            threads: 1,
+            games: 1000,
+            nodes: 5000,
+            random_plies: 8,
+            output: "data.txt",
+        }
+    }
+}
+
+/// Games that go on for longer than this are adjudicated as draws
+const MAX_PLIES: usize = 400;
+const HASH_SIZE_MB: usize = 16;
+
+/// Play self-play games and write the quiet positions in them as `FEN | score | WDL` lines,
+/// where the score is in centipawns and the WDL is the game result, both from white's point of
+/// view
+pub fn datagen(options: DatagenOptions) {
+    let file = match File::create(options.output) {
+        Ok(file) => file,
+        Err(err) => {
+            println!("info string can't create `{}`: {err}", options.output);
+            return;
+        },
+    };
+
+    let output = Mutex::new(BufWriter::new(file));
+    let games_started = AtomicUsize::new(0);
+    let games_done = AtomicUsize::new(0);
+    let positions = AtomicUsize::new(0);
+    let start = Instant::now();
+
+    std::thread::scope(|s| {
+        for index in 0..options.threads {
+            let (output, games_started, games_done, positions, options) = (&output, &games_started, &games_done, &positions, &options);
+
+            s.spawn(move || {
+                let mut rng = fastrand::Rng::with_seed(start.elapsed().as_nanos() as u64 ^ index as u64);
+                let mut engine = Engine::new(Game::default(), HASH_SIZE_MB * MB);
+                engine.limit_nodes(Some(options.nodes));
+
+                while games_started.fetch_add(1, Ordering::Relaxed) < options.games {
+                    let Some(lines) = play_game(&mut engine, &mut rng, options) else { continue };
+
+                    let mut output = output.lock();
+                    for l in lines.iter() {
+                        if let Err(err) = writeln!(output, "{l}") {
+                            println!("info string can't write to `{}`: {err}", options.output);
+                            return;
+                        }
+                    }
+                    drop(output);
+
+                    let positions = positions.fetch_add(lines.len(), Ordering::Relaxed) + lines.len();
+                    let games = games_done.fetch_add(1, Ordering::Relaxed) + 1;
+                    if games % 100 == 0 {
+                        let time = start.elapsed().as_secs_f64();
+                        println!("{games} games, {positions} positions, {:.0} positions/s", positions as f64 / time);
+                    }
+                }
+
+                engine.signals().finish();
+            });
+        }
+    });
+
+    if let Err(err) = output.lock().flush() {
+        println!("info string can't write to `{}`: {err}", options.output);
+    }
+
+    println!(
+        "{} games, {} positions in {:.2}s",
+        games_done.load(Ordering::Relaxed),
+        positions.load(Ordering::Relaxed),
+        start.elapsed().as_secs_f64(),
+    );
+}
+
+/// Play a game with a random opening and get its output lines, or [`None`] if the opening
+/// already ended the game
+fn play_game(engine: &mut Engine, rng: &mut fastrand::Rng, options: &DatagenOptions) -> Option<Vec<String>> {
+    let mut game = Game::default();
+
+    for _ in 0..options.random_plies {
+        let moves = MoveGen::new_legal(game.board()).collect::<Vec<_>>();
+        if moves.is_empty() { return None };
+
+        game.play_move(moves[rng.usize(..moves.len())]);
+    }
+
+    if game.board().status() != BoardStatus::Ongoing { return None };
+
+    engine.clear_hash();
+    let mut positions = Vec::new();
+
+    let wdl = loop {
+        match game.board().status() {
+            BoardStatus::Checkmate => break if game.board().side_to_move() == Color::White { 0.0 } else { 1.0 },
+            BoardStatus::Stalemate => break 0.5,
+            BoardStatus::Ongoing => {},
+        }
+
+        // only the kings are left
+        if game.can_declare_draw() || game.board().combined().popcnt() == 2 || positions.len() >= MAX_PLIES {
+            break 0.5;
+        }
+
+        *engine.game.write() = game.clone();
+        engine.allow_for(Duration::MAX);
+        let (mov, eval, _) = engine.best_move(|_, _| true);
+        if mov == chess::ChessMove::default() { break 0.5 };
+
+        let white_eval = if game.board().side_to_move() == Color::White { eval } else { -eval };
+        positions.push((game.get_fen(), white_eval, is_quiet(&game, mov, eval)));
+
+        game.play_move(mov);
+    };
+
+    Some(positions.into_iter()
+        .filter(|(_, _, quiet)| *quiet)
+        .map(|(fen, eval, _)| format!("{fen} | {} | {wdl:.1}", eval.0))
+        .collect())
+}
+
+/// Positions that are in check, are decided by a tactic or are already mated are too noisy to
+/// learn a static evaluation from
+fn is_quiet(game: &Game, best: chess::ChessMove, eval: Eval) -> bool {
+    game.board().checkers().0 == 0
+        && !game.is_capture(best)
+        && best.get_promotion().is_none()
+        && !eval.is_mate()
+        && eval.0.abs() < Eval::TB_WIN.0 / 2
+}
@@ -1,25 +1,67 @@
 #![feature(str_split_whitespace_remainder)]
 
-use std::{io::BufRead, str::FromStr};
+use std::{io::BufRead, str::FromStr, sync::mpsc};
 use dysprosium::*;
 
+mod bench;
 mod client;
+mod datagen;
+mod options;
 mod uci;
 
 const DEFAULT_HASH_SIZE_MB: usize = 64;
+const MAX_HASH_SIZE_MB: usize = 1 << 20;
 const DEFAULT_THREADS: usize = 1;
+const MAX_THREADS: usize = 1024;
 const MB: usize = 1024 * 1024;
 
 fn main() {
     println!("Dysprosium v{VERSION} licensed under GPLv3");
 
     let mut client = client::State::new();
+    let signals = client.signals();
 
-    let stdin = std::io::stdin().lock().lines();
-    for l in std::env::args().skip(1).map(Ok).chain(stdin) {
-        if let Ok(l) = l {
-            let tokens = l.split_whitespace();
-            client.handle_command(uci::parse_command(tokens));
+    // `dysprosium-uci bench` and `dysprosium-uci datagen` exit when they are done so that they
+    // can be scripted
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.first().is_some_and(|a| a == "bench" || a == "datagen") {
+        let command = args.join(" ");
+        client.handle_command(uci::parse_command(command.split_whitespace()));
+        return;
+    }
+
+    // commands that have to be handled while searching are handled on the input thread, the rest
+    // are passed to the main thread in order
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        for l in std::io::stdin().lock().lines() {
+            let Ok(l) = l else { break };
+
+            match l.split_whitespace().next() {
+                Some("stop") => signals.stop(),
+                Some("isready") if signals.searching() => println!("readyok"),
+                Some("ponderhit") => signals.ponderhit(),
+                Some("go") => {
+                    // a `go` that can't be parsed never runs a search that would finish it
+                    if uci::parse_command(l.split_whitespace()).is_some() {
+                        signals.start(l.split_whitespace().any(|t| t == "ponder"));
+                    }
+                    if tx.send(l).is_err() { break };
+                },
+                Some("quit") => {
+                    signals.stop();
+                    let _ = tx.send(l);
+                    break;
+                },
+                _ => if tx.send(l).is_err() { break },
+            }
         }
+
+        signals.stop();
+    });
+
+    for l in args.into_iter().chain(rx) {
+        let tokens = l.split_whitespace();
+        client.handle_command(uci::parse_command(tokens));
     }
 }
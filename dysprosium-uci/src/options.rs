@@ -0,0 +1,107 @@
+use crate::*;
+
+pub struct UciOption {
+    pub name: &'static str,
+    pub kind: OptionKind,
+}
+
+pub enum OptionKind {
+    Spin { default: i64, min: i64, max: i64 },
+    Check { default: bool },
+    Button,
+    String { default: &'static str },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionValue<'a> {
+    Spin(i64),
+    Check(bool),
+    Button,
+    String(&'a str),
+}
+
+pub const OPTIONS: &[UciOption] = &[
+    UciOption {
+        name: "Hash",
+        kind: OptionKind::Spin { default: DEFAULT_HASH_SIZE_MB as i64, min: 1, max: MAX_HASH_SIZE_MB as i64 },
+    },
+    UciOption {
+        name: "Threads",
+        kind: OptionKind::Spin { default: DEFAULT_THREADS as i64, min: 1, max: MAX_THREADS as i64 },
+    },
+    UciOption { name: "Clear Hash", kind: OptionKind::Button },
+    // pondering is controlled by `go ponder`, the option only tells the GUI we can
+    UciOption { name: "Ponder", kind: OptionKind::Check { default: false } },
+    UciOption { name: "MultiPV", kind: OptionKind::Spin { default: 1, min: 1, max: 256 } },
+    UciOption { name: "OwnBook", kind: OptionKind::Check { default: false } },
+    UciOption { name: "BookFile", kind: OptionKind::String { default: "" } },
+    UciOption { name: "BookBestMove", kind: OptionKind::Check { default: false } },
+    UciOption { name: "SyzygyPath", kind: OptionKind::String { default: "" } },
+    // empty for the embedded network, or the handcrafted evaluation if there is none
+    UciOption { name: "EvalFile", kind: OptionKind::String { default: "" } },
+];
+
+impl UciOption {
+    /// Find an option by its case insensitive name
+    pub fn find(name: &str) -> Result<&'static Self, OptionError> {
+        OPTIONS.iter()
+            .find(|o| o.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| OptionError::Unknown(name.to_string()))
+    }
+
+    pub fn parse<'a>(&self, value: Option<&'a str>) -> Result<OptionValue<'a>, OptionError> {
+        let invalid = |value: &str| OptionError::InvalidValue(self.name, value.to_string());
+
+        match self.kind {
+            OptionKind::Spin { min, max, .. } => {
+                let value = value.ok_or(OptionError::MissingValue(self.name))?;
+                let n = value.parse::<i64>().map_err(|_| invalid(value))?;
+
+                if (min..=max).contains(&n) {
+                    Ok(OptionValue::Spin(n))
+                } else {
+                    Err(OptionError::OutOfRange(self.name, n, min, max))
+                }
+            },
+            OptionKind::Check { .. } => match value.ok_or(OptionError::MissingValue(self.name))? {
+                v if v.eq_ignore_ascii_case("true") => Ok(OptionValue::Check(true)),
+                v if v.eq_ignore_ascii_case("false") => Ok(OptionValue::Check(false)),
+                v => Err(invalid(v)),
+            },
+            OptionKind::Button => Ok(OptionValue::Button),
+            OptionKind::String { .. } => Ok(OptionValue::String(value.unwrap_or(""))),
+        }
+    }
+}
+
+impl core::fmt::Display for UciOption {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "option name {} type ", self.name)?;
+
+        match self.kind {
+            OptionKind::Spin { default, min, max } => write!(f, "spin default {default} min {min} max {max}"),
+            OptionKind::Check { default } => write!(f, "check default {default}"),
+            OptionKind::Button => write!(f, "button"),
+            OptionKind::String { default } => write!(f, "string default {}", if default.is_empty() { "<empty>" } else { default }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OptionError {
+    Unknown(String),
+    MissingValue(&'static str),
+    InvalidValue(&'static str, String),
+    OutOfRange(&'static str, i64, i64, i64),
+}
+
+impl core::fmt::Display for OptionError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Unknown(name) => write!(f, "there is no option named `{name}`"),
+            Self::MissingValue(name) => write!(f, "option `{name}` needs a value"),
+            Self::InvalidValue(name, value) => write!(f, "`{value}` is not a valid value for option `{name}`"),
+            Self::OutOfRange(name, value, min, max) => write!(f, "option `{name}` has to be between {min} and {max}, got {value}"),
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use core::str::FromStr;
 use std::time::Duration;
-use chess::*;
 use dysprosium::TimeControl;
+use crate::datagen::DatagenOptions;
 
 pub enum UciCommand<'a> {
     Uci,
@@ -10,7 +10,7 @@ pub enum UciCommand<'a> {
     UciNewGame,
     Position {
         position: dysprosium::Game,
-        moves: Vec<ChessMove>,
+        moves: Vec<&'a str>,
     },
     Go {
         depth: Option<usize>,
@@ -18,31 +18,21 @@ pub enum UciCommand<'a> {
         wtime: Option<TimeControl>,
         btime: Option<TimeControl>,
         movestogo: Option<usize>,
+        infinite: bool,
+        ponder: bool,
+        nodes: Option<usize>,
+        mate: Option<i16>,
+        searchmoves: Vec<&'a str>,
     },
-    SetOption(&'a str, Option<&'a str>),
+    SetOption(String, Option<&'a str>),
     Quit,
     D,
     Eval,
-    Move(ChessMove),
-    Bench,
-}
-
-fn move_from_uci(m: &str) -> ChessMove {
-    let src = &m[0..2];
-    let src = Square::new(((src.as_bytes()[1] - b'1') << 3) + (src.as_bytes()[0] - b'a'));
-
-    let dst = &m[2..4];
-    let dst = Square::new(((dst.as_bytes()[1] - b'1') << 3) + (dst.as_bytes()[0] - b'a'));
-
-    let piece = m.as_bytes().get(4).and_then(|p| match p {
-        b'n' => Some(Piece::Knight),
-        b'b' => Some(Piece::Bishop),
-        b'q' => Some(Piece::Queen),
-        b'r' => Some(Piece::Rook),
-        _ => None,
-    });
-
-    ChessMove::new(src, dst, piece)
+    Move(&'a str),
+    Bench(Option<usize>),
+    SmpBench,
+    Perft(usize),
+    Datagen(DatagenOptions<'a>),
 }
 
 pub fn parse_command<'a>(mut token: core::str::SplitWhitespace<'a>) -> Option<UciCommand<'a>> {
@@ -66,7 +56,13 @@ pub fn parse_command<'a>(mut token: core::str::SplitWhitespace<'a>) -> Option<Uc
                     fen += " ";
                 }
 
-                dysprosium::Game::from_str(fen.trim()).ok()?
+                match dysprosium::Game::from_str(fen.trim()) {
+                    Ok(game) => game,
+                    Err(err) => {
+                        println!("info string {err}");
+                        return None;
+                    },
+                }
             } else if matches!(next, Some("startpos")) {
                 token.next();
                 dysprosium::Game::default()
@@ -74,9 +70,7 @@ pub fn parse_command<'a>(mut token: core::str::SplitWhitespace<'a>) -> Option<Uc
                 return None;
             };
 
-            while let Some(m) = token.next() {
-                moves.push(move_from_uci(m));
-            }
+            moves.extend(token);
 
             Some(UciCommand::Position {
                 position: board,
@@ -91,6 +85,12 @@ pub fn parse_command<'a>(mut token: core::str::SplitWhitespace<'a>) -> Option<Uc
             let mut winc = None;
             let mut binc = None;
             let mut movestogo = None;
+            let mut infinite = false;
+            let mut ponder = false;
+            let mut nodes = None;
+            let mut mate = None;
+            let mut searchmoves = Vec::new();
+            let mut in_searchmoves = false;
 
             while let Some(t) = token.next() {
                 match t {
@@ -101,6 +101,13 @@ pub fn parse_command<'a>(mut token: core::str::SplitWhitespace<'a>) -> Option<Uc
                     "winc" => winc = token.next().and_then(|t| t.parse().ok()),
                     "binc" => binc = token.next().and_then(|t| t.parse().ok()),
                     "movestogo" => movestogo = token.next().and_then(|t| t.parse().ok()),
+                    "infinite" => infinite = true,
+                    "ponder" => ponder = true,
+                    "nodes" => nodes = token.next().and_then(|t| t.parse().ok()),
+                    "mate" => mate = token.next().and_then(|t| t.parse().ok()),
+                    "searchmoves" => in_searchmoves = true,
+                    "perft" => return Some(UciCommand::Perft(token.next()?.parse().ok()?)),
+                    _ if in_searchmoves => searchmoves.push(t),
                     _ => {},
                 }
             }
@@ -117,20 +124,54 @@ pub fn parse_command<'a>(mut token: core::str::SplitWhitespace<'a>) -> Option<Uc
                     time_incr: binc.unwrap_or(0),
                 }),
                 movestogo,
+                infinite,
+                ponder,
+                nodes,
+                mate,
+                searchmoves,
             })
         },
         Some("setoption") => {
-            token.next();
-            let name = token.next()?;
-            token.next();
-            let value = token.remainder();
-            Some(UciCommand::SetOption(name, value))
+            if token.next()? != "name" {
+                return None;
+            }
+
+            // option names can have spaces in them
+            let mut name = Vec::new();
+            while let Some(t) = token.next() {
+                if t == "value" {
+                    break;
+                }
+
+                name.push(t);
+            }
+
+            let value = token.remainder().map(str::trim).filter(|v| !v.is_empty());
+            Some(UciCommand::SetOption(name.join(" "), value))
         },
         Some("quit") => Some(UciCommand::Quit),
         Some("d") => Some(UciCommand::D),
         Some("eval") => Some(UciCommand::Eval),
-        Some("move") => Some(UciCommand::Move(move_from_uci(token.next()?))),
-        Some("bench") => Some(UciCommand::Bench),
+        Some("move") => Some(UciCommand::Move(token.next()?)),
+        Some("bench") => Some(UciCommand::Bench(token.next().and_then(|t| t.parse().ok()))),
+        Some("smpbench") => Some(UciCommand::SmpBench),
+        Some("perft") => Some(UciCommand::Perft(token.next()?.parse().ok()?)),
+        Some("datagen") => {
+            let mut options = DatagenOptions::default();
+
+            while let Some(t) = token.next() {
+                match t {
+                    "threads" => options.threads = token.next()?.parse().ok()?,
+                    "games" => options.games = token.next()?.parse().ok()?,
+                    "nodes" => options.nodes = token.next()?.parse().ok()?,
+                    "randomplies" => options.random_plies = token.next()?.parse().ok()?,
+                    "output" => options.output = token.next()?,
+                    _ => {},
+                }
+            }
+
+            Some(UciCommand::Datagen(options))
+        },
         Some(_) => parse_command(token),
         None => None,
     }
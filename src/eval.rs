@@ -1,5 +1,10 @@
 use chess::*;
 
+mod weights;
+
+pub use weights::WEIGHTS;
+use crate::pawn_table::PawnTable;
+
 /// Evaluation score in centipawns. +ve is side to move better and -ve is worse
 /// ```text
 ///    ┌┬┬─ mate in n              ┌┬┬─ mate in !n
@@ -17,6 +22,9 @@ impl Eval {
     pub const MAX: Self = Self(i16::MAX);
     pub const MIN: Self = Self(-Self::MAX.0);
     pub const M0: Self = Self(Self::MAX.0);
+    /// Score of a position the tablebases say is won, which is above any static evaluation but
+    /// below mate scores
+    pub const TB_WIN: Self = Self(15_000);
 
     #[inline]
     pub fn incr_mate(self) -> Self {
@@ -36,6 +44,17 @@ impl Eval {
     pub fn is_positive_mate(self) -> bool {
         self.0 as u16 >> 14 == 1
     }
+
+    /// Get the amount of moves until mate, which is negative if the side to move is getting
+    /// mated
+    #[inline]
+    pub fn mate_in(self) -> Option<i16> {
+        match self.0 as u16 >> 14 {
+            1 => Some(((!self.0 & 0x3fff) + 1) / 2),
+            2 => Some(-(((self.0 & 0x3fff) + 1) / 2)),
+            _ => None,
+        }
+    }
 }
 
 impl core::ops::Add<i16> for Eval {
@@ -112,6 +131,10 @@ fn test_eval() {
     assert_eq!(m_0.to_string(), "#-0");
     assert_eq!(m_1.0 as u16, 0x8001);
     assert_eq!(m_1.to_string(), "#-1");
+    assert_eq!(m0.mate_in(), Some(0));
+    assert_eq!(m1.mate_in(), Some(1));
+    assert_eq!(m_1.mate_in(), Some(-1));
+    assert_eq!(Eval(100).mate_in(), None);
 
     let m0 = -m_0;
     assert_eq!(m0.0, 0x7fff);
@@ -126,185 +149,948 @@ fn test_eval() {
     assert_eq!(-m_1, m1);
 }
 
-/// Mostly PeSTO's evaluation with rook on open file bonus
-pub fn evaluate_static(board: &Board) -> Eval {
-    let mut mid_game = [0, 0];
-    let mut end_game = [0, 0];
-    let mut phase = 0;
+/// Evaluate with the loaded network, or with the handcrafted evaluation if there is none. The
+/// accumulator is built from scratch, searches keep their own up to date instead.
+pub fn evaluate(game: &crate::Game) -> Eval {
+    match crate::nnue::network() {
+        Some(net) => net.evaluate(&crate::nnue::Accumulator::new(&net, game.board()), game.board().side_to_move()),
+        None => evaluate_static(game),
+    }
+}
+
+/// [`evaluate_static`] that keeps the pawn structure evaluation in `pawn_table`
+pub(crate) fn evaluate_with(game: &crate::Game, pawn_table: Option<&mut PawnTable>) -> Eval {
+    evaluate_board(game.board(), &mut (), pawn_table, Some(game.psqt()))
+}
+
+/// The handcrafted evaluation, starting from the material and piece square table scores the
+/// game keeps up to date
+pub fn evaluate_static(game: &crate::Game) -> Eval {
+    evaluate_board(game.board(), &mut (), None, Some(game.psqt()))
+}
+
+/// [`evaluate_static`] from scratch that reports every weight it uses to `trace`
+pub fn evaluate_traced<T: Trace>(board: &Board, trace: &mut T) -> Eval {
+    // cached pawn structure and incremental piece square tables can't be traced
+    evaluate_board(board, trace, None, None)
+}
+
+/// Material and piece square table score of each color, which is kept by [`Game`](crate::Game)
+/// so that it doesn't have to be summed up for every evaluation
+pub fn psqt(board: &Board) -> [S; 2] {
+    let mut eval = Evaluator { trace: &mut (), score: [S::default(); 2] };
+    material_psqt(board, &mut eval);
+    eval.score
+}
+
+/// Apply the changes of a move to [`psqt`] scores
+#[inline]
+pub fn update_psqt(mut psqt: [S; 2], changes: &[crate::game::PieceChange]) -> [S; 2] {
+    for change in changes {
+        let index = psqt_index(change.piece, change.color, change.square);
+        let score = WEIGHTS[MATERIAL + change.piece.to_index()] + WEIGHTS[PSQT + index];
+
+        if change.add {
+            psqt[change.color.to_index()] += score;
+        } else {
+            psqt[change.color.to_index()] -= score;
+        }
+    }
+
+    psqt
+}
+
+/// Evaluate from scratch, or with the material and piece square table scores in `psqt`
+fn evaluate_board<T: Trace>(board: &Board, trace: &mut T, pawn_table: Option<&mut PawnTable>, psqt: Option<[S; 2]>) -> Eval {
+    let mut eval = Evaluator { trace, score: psqt.unwrap_or_default() };
+    if psqt.is_none() {
+        material_psqt(board, &mut eval);
+    }
+
+    for color in [Color::White, Color::Black] {
+        // TODO: open file penalty fails SPRT
+        //
+        // let mut open_files = 0;
+        // if let Some(sq) = square.left() {
+        //     open_files += ((board.pieces(Piece::Pawn) & board.color_combined(color) & chess::get_file(sq.get_file())).0 == 0) as i16;
+        // }
+        // if let Some(sq) = square.right() {
+        //     open_files += ((board.pieces(Piece::Pawn) & board.color_combined(color) & chess::get_file(sq.get_file())).0 == 0) as i16;
+        // }
+
+        let king_center = board.king_square(color).uforward(color);
+        let king_pawns = (board.pieces(Piece::Pawn) & (chess::get_king_moves(king_center) | BitBoard::from_square(king_center))).popcnt();
+        // more than 3 shield pawns don't make the king any safer
+        eval.add(KING_PAWN_SHIELD, color, king_pawns.min(3) as i16);
+    }
+
+    let phase = game_phase(board);
+
+    let passed = match pawn_table {
+        Some(table) => {
+            let entry = table.get_or_insert_with(board.get_pawn_hash(), || {
+                let mut pawns = Evaluator { trace: &mut (), score: [S::default(); 2] };
+                let passed = pawn_structure(board, &mut pawns);
+                (pawns.score, passed)
+            });
+
+            eval.score[0] += entry.score[0];
+            eval.score[1] += entry.score[1];
+            entry.passed
+        },
+        None => pawn_structure(board, &mut eval),
+    };
+    passed_pawn_kings(board, passed, &mut eval);
+    mobility_and_king_attacks(board, &mut eval);
+    piece_placement(board, &mut eval);
+    threats(board, &mut eval);
+
+    let score = eval.score[0] - eval.score[1];
+    let scale = endgame_scale(board, score);
+    let mop_up = mop_up(board);
+    eval.trace.endgame(scale, mop_up);
+
+    let white_eval = combine(score, phase, scale, mop_up);
+    if board.side_to_move() == Color::White { white_eval } else { -white_eval }
+}
+
+/// Get the evaluation from white's point of view from the score of white minus black's, with
+/// the endgame part scaled and the mop up added
+#[inline]
+pub fn combine(score: S, phase: u8, scale: i16, mop_up: i16) -> Eval {
+    let eg = (score.1 as i32 * scale as i32 / SCALE_NORMAL as i32) as i16;
+    taper(S(score.0, eg), phase) + mop_up
+}
+
+/// Scale of the endgame part of the score out of [`SCALE_NORMAL`], which is lower in endings
+/// that are more drawn than the material says
+fn endgame_scale(board: &Board, score: S) -> i16 {
+    let strong = if score.1 >= 0 { Color::White } else { Color::Black };
+    let count = |piece, color| (board.pieces(piece) & board.color_combined(color)).popcnt() as i16;
+    let material = |color| 3 * (count(Piece::Knight, color) + count(Piece::Bishop, color))
+        + 5 * count(Piece::Rook, color)
+        + 9 * count(Piece::Queen, color);
+
+    if count(Piece::Pawn, strong) == 0 {
+        // a lone minor piece can't mate
+        if material(strong) <= 3 {
+            return 0;
+        }
+
+        // neither can two knights against a bare king
+        if count(Piece::Knight, strong) == 2 && material(strong) == 6 && count(Piece::Pawn, !strong) == 0 {
+            return 0;
+        }
+
+        // being up no more than a minor piece without pawns is usually a draw
+        if material(strong) - material(!strong) <= 3 {
+            return 16;
+        }
+    }
+
+    let bishops = [Color::White, Color::Black].map(|c| board.pieces(Piece::Bishop) & board.color_combined(c));
+    if bishops[0].popcnt() == 1 && bishops[1].popcnt() == 1 && is_light(bishops[0].to_square()) != is_light(bishops[1].to_square()) {
+        // opposite colored bishops are drawish, more so without other pieces
+        return if material(Color::White) == 3 && material(Color::Black) == 3 { 24 } else { 48 };
+    }
+
+    if is_wrong_bishop(board, strong) {
+        return 0;
+    }
+
+    SCALE_NORMAL
+}
+
+/// Only rook pawns with a bishop that doesn't control the promotion square, which the enemy king
+/// is guarding
+fn is_wrong_bishop(board: &Board, strong: Color) -> bool {
+    let pawns = board.pieces(Piece::Pawn) & board.color_combined(strong);
+    let bishops = board.pieces(Piece::Bishop) & board.color_combined(strong);
+    // nothing else but the king
+    if pawns.0 == 0 || bishops.popcnt() != 1 || board.color_combined(strong).popcnt() != pawns.popcnt() + 2 {
+        return false;
+    }
 
+    let file = if (pawns & !chess::get_file(File::A)).0 == 0 {
+        File::A
+    } else if (pawns & !chess::get_file(File::H)).0 == 0 {
+        File::H
+    } else {
+        return false;
+    };
+    let promotion = Square::make_square(if strong == Color::White { Rank::Eighth } else { Rank::First }, file);
+
+    is_light(bishops.to_square()) != is_light(promotion) && distance(board.king_square(!strong), promotion) <= 1
+}
+
+/// Drive the bare king to the edge, or the corner the bishop controls in KBNK, and bring the
+/// other king closer, which the search is too shallow to see in KQK, KRK and KBNK. From white's
+/// point of view.
+fn mop_up(board: &Board) -> i16 {
+    for strong in [Color::White, Color::Black] {
+        if board.color_combined(!strong).popcnt() != 1 { continue };
+
+        let pieces = |piece| board.pieces(piece) & board.color_combined(strong);
+        let strong_king = board.king_square(strong);
+        let weak_king = board.king_square(!strong);
+
+        let edge = if (pieces(Piece::Queen) | pieces(Piece::Rook)).0 != 0 {
+            center_distance(weak_king) * 10
+        } else if pieces(Piece::Bishop).0 != 0 && pieces(Piece::Knight).0 != 0 {
+            let corners = if is_light(pieces(Piece::Bishop).to_square()) {
+                [Square::H1, Square::A8]
+            } else {
+                [Square::A1, Square::H8]
+            };
+            let corner_distance = distance(weak_king, corners[0]).min(distance(weak_king, corners[1]));
+            (7 - corner_distance) * 10
+        } else {
+            continue;
+        };
+
+        let bonus = edge + (14 - manhattan_distance(strong_king, weak_king)) * 4;
+        return if strong == Color::White { bonus } else { -bonus };
+    }
+
+    0
+}
+
+fn material_psqt<T: Trace>(board: &Board, eval: &mut Evaluator<T>) {
     for square in board.combined().into_iter() {
         // SAFETY: only squares with things on it are checked
         let piece = unsafe { board.piece_on(square).unwrap_unchecked() };
         let color = unsafe { board.color_on(square).unwrap_unchecked() };
 
-        // rook on open file bonus
-        let rook_on_open_file = (piece == Piece::Rook
-            && (board.pieces(Piece::Pawn) & chess::get_file(square.get_file())).0 == 0
-        ) as i16 * 20;
-        let pawn_shield = if piece == Piece::King {
-            // TODO: open file penalty fails SPRT
-            //
-            // let mut open_files = 0;
-            // if let Some(sq) = square.left() {
-            //     open_files += ((board.pieces(Piece::Pawn) & board.color_combined(color) & chess::get_file(sq.get_file())).0 == 0) as i16;
-            // }
-            // if let Some(sq) = square.right() {
-            //     open_files += ((board.pieces(Piece::Pawn) & board.color_combined(color) & chess::get_file(sq.get_file())).0 == 0) as i16;
-            // }
-
-            let king_center = square.uforward(color);
-            let king_pawns = (board.pieces(Piece::Pawn) & (chess::get_king_moves(king_center) | BitBoard::from_square(king_center))).popcnt();
-
-            -(3_i16.saturating_sub(king_pawns as i16) * 15) // + open_files * 50)
-        } else { 0 };
-
-        let idx = (square.to_index() ^ (0b111_000 * (color == Color::Black) as usize)) | (piece.to_index() << 6);
-        mid_game[color.to_index()] += rook_on_open_file + pawn_shield + PIECE_SQUARE_TABLE_MID[idx] + PIECE_VALUE_MID[piece.to_index()];
-        end_game[color.to_index()] += rook_on_open_file + PIECE_SQUARE_TABLE_END[idx] + PIECE_VALUE_END[piece.to_index()];
-        phase += PIECE_PHASE[piece.to_index()];
-    }
-
-    let stm = board.side_to_move() as usize;
-    let mg_eval = mid_game[stm] - mid_game[1 - stm];
-    let eg_eval = end_game[stm] - end_game[1 - stm];
-    let mg_phase = phase.min(24);
+        eval.add(MATERIAL + piece.to_index(), color, 1);
+        eval.add(PSQT + psqt_index(piece, color, square), color, 1);
+    }
+}
+
+/// Index of a piece in the piece square tables, which are from white's point of view
+#[inline]
+fn psqt_index(piece: Piece, color: Color, square: Square) -> usize {
+    (square.to_index() ^ (0b111_000 * (color == Color::Black) as usize)) | (piece.to_index() << 6)
+}
+
+/// Evaluate the pawns of both sides, which only depends on where the pawns are, and get the
+/// passed pawns
+fn pawn_structure<T: Trace>(board: &Board, eval: &mut Evaluator<T>) -> BitBoard {
+    let pawns = board.pieces(Piece::Pawn);
+    let mut passed = BitBoard(0);
+
+    for color in [Color::White, Color::Black] {
+        let ours = pawns & board.color_combined(color);
+        let theirs = pawns & board.color_combined(!color);
+
+        for square in ours {
+            let file = chess::get_file(square.get_file());
+            let adjacent = chess::get_adjacent_files(square.get_file());
+            let ahead = ranks_ahead(color, square.get_rank());
+            let rank = relative_rank(color, square.get_rank());
+
+            if (theirs & (file | adjacent) & ahead).0 == 0 {
+                eval.add(PASSED_PAWN + rank, color, 1);
+                passed |= BitBoard::from_square(square);
+            }
+
+            // only the pawns behind count as doubled so that each extra pawn counts once
+            if (ours & file & ahead).0 != 0 {
+                eval.add(DOUBLED_PAWN, color, 1);
+            }
+
+            if (ours & adjacent).0 == 0 {
+                eval.add(ISOLATED_PAWN, color, 1);
+            } else if (ours & adjacent & !ahead).0 == 0
+                && chess::get_pawn_attacks(square.uforward(color), color, theirs).0 != 0
+            {
+                // every neighbour has gone ahead and it can't advance safely to join them
+                eval.add(BACKWARD_PAWN, color, 1);
+            }
+
+            let phalanx = ours & adjacent & chess::get_rank(square.get_rank());
+            let supporters = chess::get_pawn_attacks(square, !color, ours);
+            if (phalanx | supporters).0 != 0 {
+                eval.add(CONNECTED_PAWN + rank, color, 1);
+            }
+        }
+    }
+
+    passed
+}
+
+/// Kings matter a lot for passed pawns in endgames, so the distance from both kings to the
+/// square in front of every passed pawn is scored
+fn passed_pawn_kings<T: Trace>(board: &Board, passed: BitBoard, eval: &mut Evaluator<T>) {
+    for square in passed {
+        // SAFETY: passed pawns are pawns on the board
+        let color = unsafe { board.color_on(square).unwrap_unchecked() };
+        let stop = square.uforward(color);
+
+        eval.add(PASSED_KING_DISTANCE, color, distance(board.king_square(color), stop));
+        eval.add(PASSED_ENEMY_KING_DISTANCE, color, distance(board.king_square(!color), stop));
+    }
+}
+
+/// Score the safe squares every piece can go to and the attacks on the king zone, which is the
+/// king and the squares next to it. King attacks only count with at least 2 attackers and are
+/// scored by the attack units of the attackers, which is how many king zone squares each of them
+/// attacks weighted by the piece.
+fn mobility_and_king_attacks<T: Trace>(board: &Board, eval: &mut Evaluator<T>) {
+    const PIECES: [(Piece, usize, u32); 4] = [
+        (Piece::Knight, KNIGHT_MOBILITY, 2),
+        (Piece::Bishop, BISHOP_MOBILITY, 2),
+        (Piece::Rook, ROOK_MOBILITY, 3),
+        (Piece::Queen, QUEEN_MOBILITY, 5),
+    ];
+
+    let occupied = *board.combined();
+
+    for color in [Color::White, Color::Black] {
+        let enemy_pawns = board.pieces(Piece::Pawn) & board.color_combined(!color);
+        let safe = !*board.color_combined(color) & !pawn_attacks(!color, enemy_pawns);
+
+        let enemy_king = board.king_square(!color);
+        let king_zone = chess::get_king_moves(enemy_king) | BitBoard::from_square(enemy_king);
+        let mut attackers = 0;
+        let mut attack_units = 0;
+
+        for (piece, mobility, units) in PIECES {
+            for square in board.pieces(piece) & board.color_combined(color) {
+                let attacks = piece_attacks(piece, square, occupied);
+                eval.add(mobility + (attacks & safe).popcnt() as usize, color, 1);
+
+                let zone_attacks = (attacks & king_zone).popcnt();
+                if zone_attacks != 0 {
+                    attackers += 1;
+                    attack_units += units * zone_attacks;
+                }
+            }
+        }
+
+        if attackers >= 2 {
+            eval.add(KING_ATTACK + (attack_units as usize).min(KING_ATTACK_UNITS - 1), color, 1);
+        }
+    }
+}
+
+/// Bishop pair, outposts and rook placement
+fn piece_placement<T: Trace>(board: &Board, eval: &mut Evaluator<T>) {
+    let occupied = *board.combined();
+    let pawns = board.pieces(Piece::Pawn);
+
+    for color in [Color::White, Color::Black] {
+        let ours = *board.color_combined(color);
+        let our_pawns = pawns & ours;
+        let their_pawns = pawns & board.color_combined(!color);
+
+        if (board.pieces(Piece::Bishop) & ours).popcnt() >= 2 {
+            eval.add(BISHOP_PAIR, color, 1);
+        }
+
+        // squares on our 4th to 6th ranks that our pawns defend and their pawns can never attack
+        let attackable = their_pawns.fold(BitBoard(0), |acc, square| {
+            acc | (chess::get_adjacent_files(square.get_file()) & ranks_ahead(!color, square.get_rank()))
+        });
+        let outpost_ranks = [3, 4, 5].into_iter().fold(BitBoard(0), |acc, rank| acc | relative_rank_squares(color, rank));
+        let outposts = pawn_attacks(color, our_pawns) & outpost_ranks & !attackable;
+        eval.add(OUTPOST, color, (board.pieces(Piece::Knight) & ours & outposts).popcnt() as i16);
+        eval.add(OUTPOST + 1, color, (board.pieces(Piece::Bishop) & ours & outposts).popcnt() as i16);
+
+        let rooks = board.pieces(Piece::Rook) & ours;
+        for square in rooks {
+            let file = chess::get_file(square.get_file());
+
+            if (pawns & file).0 == 0 {
+                eval.add(ROOK_OPEN_FILE, color, 1);
+            } else if (our_pawns & file).0 == 0 {
+                eval.add(ROOK_SEMI_OPEN_FILE, color, 1);
+            }
+
+            // the seventh rank only matters when there are pawns to eat or a king to trap
+            if relative_rank(color, square.get_rank()) == 6
+                && ((their_pawns & relative_rank_squares(color, 6)).0 != 0
+                    || relative_rank(color, board.king_square(!color).get_rank()) == 7)
+            {
+                eval.add(ROOK_SEVENTH, color, 1);
+            }
+        }
+
+        if rooks.into_iter().any(|square| (chess::get_rook_moves(square, occupied) & rooks).0 != 0) {
+            eval.add(CONNECTED_ROOKS, color, 1);
+        }
+    }
+}
+
+/// Enemy pieces attacked by pawns, rooks and queens attacked by minor pieces, undefended pieces
+/// that are attacked and pawn pushes that would attack pieces
+fn threats<T: Trace>(board: &Board, eval: &mut Evaluator<T>) {
+    let occupied = *board.combined();
+    let pawns = board.pieces(Piece::Pawn);
+    let attacked = [Color::White, Color::Black].map(|c| attacked_squares(board, c, occupied));
+
+    for color in [Color::White, Color::Black] {
+        let ours = *board.color_combined(color);
+        let theirs = *board.color_combined(!color);
+        let their_pieces = theirs & !*pawns & !*board.pieces(Piece::King);
+        let our_pawns = pawns & ours;
+        let our_attacks = attacked[color.to_index()];
+        let their_attacks = attacked[(!color).to_index()];
+
+        let by_pawns = pawn_attacks(color, our_pawns) & their_pieces;
+        for piece in [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen] {
+            eval.add(THREAT_BY_PAWN + piece.to_index() - 1, color, (by_pawns & board.pieces(piece)).popcnt() as i16);
+        }
+
+        let by_minors = (board.pieces(Piece::Knight) | board.pieces(Piece::Bishop)) & ours;
+        let minor_attacks = by_minors.fold(BitBoard(0), |acc, square| {
+            // SAFETY: only squares with minor pieces are checked
+            acc | piece_attacks(unsafe { board.piece_on(square).unwrap_unchecked() }, square, occupied)
+        });
+        eval.add(THREAT_BY_MINOR, color, (minor_attacks & theirs & board.pieces(Piece::Rook)).popcnt() as i16);
+        eval.add(THREAT_BY_MINOR + 1, color, (minor_attacks & theirs & board.pieces(Piece::Queen)).popcnt() as i16);
+
+        eval.add(HANGING, color, (their_pieces & our_attacks & !their_attacks).popcnt() as i16);
+
+        // pushes to squares enemy pawns don't attack and that are defended or not attacked
+        let pushes = match color {
+            Color::White => BitBoard(our_pawns.0 << 8),
+            Color::Black => BitBoard(our_pawns.0 >> 8),
+        } & !occupied;
+        let safe = !pawn_attacks(!color, pawns & theirs) & (our_attacks | !their_attacks);
+        let push_threats = pawn_attacks(color, pushes & safe) & their_pieces & !by_pawns;
+        eval.add(PAWN_PUSH_THREAT, color, push_threats.popcnt() as i16);
+    }
+}
+
+/// Every square attacked by the pieces of `color`
+fn attacked_squares(board: &Board, color: Color, occupied: BitBoard) -> BitBoard {
+    let ours = board.color_combined(color);
+    let mut attacks = pawn_attacks(color, board.pieces(Piece::Pawn) & ours)
+        | chess::get_king_moves(board.king_square(color));
+
+    for piece in [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen] {
+        for square in board.pieces(piece) & ours {
+            attacks |= piece_attacks(piece, square, occupied);
+        }
+    }
+
+    attacks
+}
+
+/// Squares attacked by a knight, bishop, rook or queen
+#[inline]
+fn piece_attacks(piece: Piece, square: Square, occupied: BitBoard) -> BitBoard {
+    match piece {
+        Piece::Knight => chess::get_knight_moves(square),
+        Piece::Bishop => chess::get_bishop_moves(square, occupied),
+        Piece::Rook => chess::get_rook_moves(square, occupied),
+        Piece::Queen => chess::get_bishop_moves(square, occupied) | chess::get_rook_moves(square, occupied),
+        _ => BitBoard(0),
+    }
+}
+
+/// Squares attacked by the pawns of `color`
+#[inline]
+fn pawn_attacks(color: Color, pawns: BitBoard) -> BitBoard {
+    pawns.fold(BitBoard(0), |acc, square| acc | chess::get_pawn_attacks(square, color, !BitBoard(0)))
+}
+
+/// Squares on the ranks in front of `rank` from the point of view of `color`
+#[inline]
+fn ranks_ahead(color: Color, rank: Rank) -> BitBoard {
+    match color {
+        Color::White => BitBoard(!0_u64 << 8 << (rank.to_index() * 8)),
+        Color::Black => BitBoard((1_u64 << (rank.to_index() * 8)) - 1),
+    }
+}
+
+/// Squares on the rank from 0 to 7 from the point of view of `color`
+#[inline]
+fn relative_rank_squares(color: Color, rank: usize) -> BitBoard {
+    match color {
+        Color::White => chess::get_rank(Rank::from_index(rank)),
+        Color::Black => chess::get_rank(Rank::from_index(7 - rank)),
+    }
+}
+
+/// Rank from 0 to 7 from the point of view of `color`
+#[inline]
+fn relative_rank(color: Color, rank: Rank) -> usize {
+    match color {
+        Color::White => rank.to_index(),
+        Color::Black => 7 - rank.to_index(),
+    }
+}
+
+#[inline]
+fn is_light(square: Square) -> bool {
+    (square.get_file().to_index() + square.get_rank().to_index()) % 2 == 1
+}
+
+/// How many files and ranks a square is away from the 4 center squares
+#[inline]
+fn center_distance(square: Square) -> i16 {
+    let file = square.get_file().to_index() as i16;
+    let rank = square.get_rank().to_index() as i16;
+    (3 - file).max(file - 4) + (3 - rank).max(rank - 4)
+}
+
+#[inline]
+fn manhattan_distance(a: Square, b: Square) -> i16 {
+    (a.get_file().to_index() as i16 - b.get_file().to_index() as i16).abs()
+        + (a.get_rank().to_index() as i16 - b.get_rank().to_index() as i16).abs()
+}
+
+/// Chebyshev distance, which is how many king moves it takes to go between the squares
+#[inline]
+fn distance(a: Square, b: Square) -> i16 {
+    let files = (a.get_file().to_index() as i16 - b.get_file().to_index() as i16).abs();
+    let ranks = (a.get_rank().to_index() as i16 - b.get_rank().to_index() as i16).abs();
+    files.max(ranks)
+}
+
+/// Blend the midgame and endgame parts of a score by the phase of the game
+#[inline]
+pub fn taper(score: S, phase: u8) -> Eval {
+    let mg_phase = phase.min(24) as i32;
     let eg_phase = 24 - mg_phase;
 
-    Eval(((mg_eval as i32 * mg_phase as i32 + eg_eval as i32 * eg_phase as i32) / 24) as i16)
+    Eval(((score.0 as i32 * mg_phase + score.1 as i32 * eg_phase) / 24) as i16)
 }
 
 /// Finds the current phase of the game. 0 is endgame and 24 is midgame.
 pub fn game_phase(board: &Board) -> u8 {
-    board.combined().into_iter().map(|sq| PIECE_PHASE[unsafe { board.piece_on(sq).unwrap_unchecked() }.to_index()]).sum::<u8>().min(24)
+    ALL_PIECES.iter().map(|p| PIECE_PHASE[p.to_index()] * board.pieces(*p).popcnt() as u8).sum::<u8>().min(24)
+}
+
+/// A midgame and endgame score pair
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct S(pub i16, pub i16);
+
+impl core::ops::Add for S {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self::Output {
+        Self(self.0 + rhs.0, self.1 + rhs.1)
+    }
+}
+
+impl core::ops::AddAssign for S {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl core::ops::SubAssign for S {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl core::ops::Sub for S {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self(self.0 - rhs.0, self.1 - rhs.1)
+    }
+}
+
+impl core::ops::Mul<i16> for S {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: i16) -> Self::Output {
+        Self(self.0 * rhs, self.1 * rhs)
+    }
+}
+
+/// Gets told about every weight the evaluation uses, to tune or explain it
+pub trait Trace {
+    /// `color` got the weight at `index` `count` times
+    fn add(&mut self, index: usize, color: Color, count: i16);
+
+    /// The endgame adjustments that aren't weights, see [`combine`]
+    #[inline(always)]
+    fn endgame(&mut self, _scale: i16, _mop_up: i16) {}
+}
+
+/// Not tracing at all, which compiles down to the plain evaluation
+impl Trace for () {
+    #[inline(always)]
+    fn add(&mut self, _: usize, _: Color, _: i16) {}
+}
+
+struct Evaluator<'a, T: Trace> {
+    trace: &'a mut T,
+    /// Score of each color
+    score: [S; 2],
+}
+
+impl<T: Trace> Evaluator<'_, T> {
+    #[inline(always)]
+    fn add(&mut self, index: usize, color: Color, count: i16) {
+        self.score[color.to_index()] += WEIGHTS[index] * count;
+        self.trace.add(index, color, count);
+    }
+}
+
+/// Per term and per side breakdown of [`evaluate_static`], which is traced from the evaluation
+/// itself so that it always adds up to the real score
+pub struct Breakdown {
+    /// Score of every term in [`TERMS`] for each color
+    pub terms: Vec<[S; 2]>,
+    pub phase: u8,
+    /// Endgame scale out of [`SCALE_NORMAL`]
+    pub scale: i16,
+    /// Mop up score from white's point of view
+    pub mop_up: i16,
+    /// The evaluation from the point of view of the side to move
+    pub eval: Eval,
+    pub side_to_move: Color,
+}
+
+impl Breakdown {
+    pub fn new(board: &Board) -> Self {
+        let mut breakdown = Self {
+            terms: vec![[S::default(); 2]; TERMS.len()],
+            phase: game_phase(board),
+            scale: SCALE_NORMAL,
+            mop_up: 0,
+            eval: Eval(0),
+            side_to_move: board.side_to_move(),
+        };
+        breakdown.eval = evaluate_traced(board, &mut breakdown);
+
+        debug_assert_eq!(breakdown.white_eval(), if breakdown.side_to_move == Color::White {
+            breakdown.eval
+        } else {
+            -breakdown.eval
+        });
+
+        breakdown
+    }
+
+    /// Sum of all terms of a color
+    pub fn total(&self, color: Color) -> S {
+        self.terms.iter().fold(S::default(), |acc, t| acc + t[color.to_index()])
+    }
+
+    /// The evaluation from white's point of view, put together from the breakdown alone
+    pub fn white_eval(&self) -> Eval {
+        combine(self.total(Color::White) - self.total(Color::Black), self.phase, self.scale, self.mop_up)
+    }
+}
+
+impl Trace for Breakdown {
+    fn add(&mut self, index: usize, color: Color, count: i16) {
+        let term = TERMS.iter().position(|(_, start, len)| (*start..start + len).contains(&index)).unwrap();
+        self.terms[term][color.to_index()] += WEIGHTS[index] * count;
+    }
+
+    fn endgame(&mut self, scale: i16, mop_up: i16) {
+        self.scale = scale;
+        self.mop_up = mop_up;
+    }
+}
+
+impl core::fmt::Display for Breakdown {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        fn row(f: &mut core::fmt::Formatter<'_>, name: &str, white: S, black: S) -> core::fmt::Result {
+            let total = white - black;
+            writeln!(
+                f,
+                "│ {name:<22} │ {:>5} {:>5} │ {:>5} {:>5} │ {:>5} {:>5} │",
+                white.0, white.1, black.0, black.1, total.0, total.1,
+            )
+        }
+
+        writeln!(f, "┌────────────────────────┬─────────────┬─────────────┬─────────────┐")?;
+        writeln!(f, "│ Term                   │    White    │    Black    │    Total    │")?;
+        writeln!(f, "│                        │    MG    EG │    MG    EG │    MG    EG │")?;
+        writeln!(f, "├────────────────────────┼─────────────┼─────────────┼─────────────┤")?;
+        for ((name, _, _), [white, black]) in TERMS.iter().zip(self.terms.iter()) {
+            row(f, name, *white, *black)?;
+        }
+        writeln!(f, "├────────────────────────┼─────────────┼─────────────┼─────────────┤")?;
+        row(f, "Total", self.total(Color::White), self.total(Color::Black))?;
+        writeln!(f, "└────────────────────────┴─────────────┴─────────────┴─────────────┘")?;
+
+        writeln!(f, "Phase: {}/24 (24 is midgame)", self.phase)?;
+        writeln!(f, "Endgame scale: {}/{SCALE_NORMAL}", self.scale)?;
+        writeln!(f, "Mop up: {}", self.mop_up)?;
+        writeln!(f, "Handcrafted evaluation: {} (white side)", self.white_eval())
+    }
 }
 
+/// Material of each piece, indexed by piece
+pub const MATERIAL: usize = 0;
+/// Piece square tables from white's point of view, indexed by `square | piece << 6`
+pub const PSQT: usize = MATERIAL + 6;
+pub const ROOK_OPEN_FILE: usize = PSQT + 64 * 6;
+/// Per pawn in front of or next to the king
+pub const KING_PAWN_SHIELD: usize = ROOK_OPEN_FILE + 1;
+/// Indexed by the rank from the point of view of the pawn
+pub const PASSED_PAWN: usize = KING_PAWN_SHIELD + 1;
+/// Per square between the own king and the square in front of a passed pawn
+pub const PASSED_KING_DISTANCE: usize = PASSED_PAWN + 8;
+/// Per square between the enemy king and the square in front of a passed pawn
+pub const PASSED_ENEMY_KING_DISTANCE: usize = PASSED_KING_DISTANCE + 1;
+pub const ISOLATED_PAWN: usize = PASSED_ENEMY_KING_DISTANCE + 1;
+/// Per pawn with another pawn of the same color in front of it
+pub const DOUBLED_PAWN: usize = ISOLATED_PAWN + 1;
+pub const BACKWARD_PAWN: usize = DOUBLED_PAWN + 1;
+/// Pawns defended by or next to another pawn, indexed by the rank from the point of view of
+/// the pawn
+pub const CONNECTED_PAWN: usize = BACKWARD_PAWN + 1;
+/// Mobility indexed by the amount of squares a piece can go to that aren't attacked by enemy
+/// pawns
+pub const KNIGHT_MOBILITY: usize = CONNECTED_PAWN + 8;
+pub const BISHOP_MOBILITY: usize = KNIGHT_MOBILITY + 9;
+pub const ROOK_MOBILITY: usize = BISHOP_MOBILITY + 14;
+pub const QUEEN_MOBILITY: usize = ROOK_MOBILITY + 15;
+/// Indexed by the attack units on the enemy king zone, see [`KING_ATTACK_UNITS`]
+pub const KING_ATTACK: usize = QUEEN_MOBILITY + 28;
+/// Attack units above this are counted as this
+pub const KING_ATTACK_UNITS: usize = 32;
+pub const BISHOP_PAIR: usize = KING_ATTACK + KING_ATTACK_UNITS;
+/// Knights then bishops on squares in the enemy half that are defended by pawns and can't be
+/// attacked by enemy pawns
+pub const OUTPOST: usize = BISHOP_PAIR + 1;
+/// Files with enemy pawns but not our pawns
+pub const ROOK_SEMI_OPEN_FILE: usize = OUTPOST + 2;
+/// Only with enemy pawns on the seventh rank or the enemy king on the eighth
+pub const ROOK_SEVENTH: usize = ROOK_SEMI_OPEN_FILE + 1;
+pub const CONNECTED_ROOKS: usize = ROOK_SEVENTH + 1;
+/// Enemy knights, bishops, rooks then queens attacked by pawns
+pub const THREAT_BY_PAWN: usize = CONNECTED_ROOKS + 1;
+/// Enemy rooks then queens attacked by knights or bishops
+pub const THREAT_BY_MINOR: usize = THREAT_BY_PAWN + 4;
+/// Enemy pieces other than pawns that are attacked and not defended
+pub const HANGING: usize = THREAT_BY_MINOR + 2;
+/// Enemy pieces that a safe pawn push would attack
+pub const PAWN_PUSH_THREAT: usize = HANGING + 1;
+pub const NUM_WEIGHTS: usize = PAWN_PUSH_THREAT + 1;
+
+/// Name, first weight and amount of weights of every evaluation term
+pub const TERMS: &[(&str, usize, usize)] = &[
+    ("Material", MATERIAL, 6),
+    ("Piece square tables", PSQT, 64 * 6),
+    ("Rook on open file", ROOK_OPEN_FILE, 1),
+    ("King pawn shield", KING_PAWN_SHIELD, 1),
+    ("Passed pawn", PASSED_PAWN, 8),
+    ("Passed pawn king", PASSED_KING_DISTANCE, 1),
+    ("Passed pawn enemy king", PASSED_ENEMY_KING_DISTANCE, 1),
+    ("Isolated pawn", ISOLATED_PAWN, 1),
+    ("Doubled pawn", DOUBLED_PAWN, 1),
+    ("Backward pawn", BACKWARD_PAWN, 1),
+    ("Connected pawn", CONNECTED_PAWN, 8),
+    ("Knight mobility", KNIGHT_MOBILITY, 9),
+    ("Bishop mobility", BISHOP_MOBILITY, 14),
+    ("Rook mobility", ROOK_MOBILITY, 15),
+    ("Queen mobility", QUEEN_MOBILITY, 28),
+    ("King attack", KING_ATTACK, KING_ATTACK_UNITS),
+    ("Bishop pair", BISHOP_PAIR, 1),
+    ("Outpost", OUTPOST, 2),
+    ("Rook on semi-open file", ROOK_SEMI_OPEN_FILE, 1),
+    ("Rook on seventh", ROOK_SEVENTH, 1),
+    ("Connected rooks", CONNECTED_ROOKS, 1),
+    ("Threat by pawn", THREAT_BY_PAWN, 4),
+    ("Threat by minor", THREAT_BY_MINOR, 2),
+    ("Hanging piece", HANGING, 1),
+    ("Pawn push threat", PAWN_PUSH_THREAT, 1),
+];
+
 pub const PIECE_VALUE: [i16; 6] = [
-    PIECE_VALUE_MID[0],
-    PIECE_VALUE_MID[1],
-    PIECE_VALUE_MID[2],
-    PIECE_VALUE_MID[3],
-    PIECE_VALUE_MID[4],
+    WEIGHTS[MATERIAL].0,
+    WEIGHTS[MATERIAL + 1].0,
+    WEIGHTS[MATERIAL + 2].0,
+    WEIGHTS[MATERIAL + 3].0,
+    WEIGHTS[MATERIAL + 4].0,
     200_00,
 ];
 
-const PIECE_VALUE_MID: [i16; 6] = [82, 337, 365, 477, 1025,  0];
-const PIECE_VALUE_END: [i16; 6] = [94, 281, 297, 512,  936,  0];
 const PIECE_PHASE: [u8; 6] = [0, 1, 1, 2, 4, 0];
 
-// a1 ----> h1
-// |
-// v
-// a8
-const PIECE_SQUARE_TABLE_MID: [i16; 64 * 6] = [
-    // Pawn
-      0,   0,   0,   0,   0,   0,  0,   0,
-    -35,  -1, -20, -23, -15,  24, 38, -22,
-    -26,  -4,  -4, -10,   3,   3, 33, -12,
-    -27,  -2,  -5,  12,  17,   6, 10, -25,
-    -14,  13,   6,  21,  23,  12, 17, -23,
-     -6,   7,  26,  31,  65,  56, 25, -20,
-     98, 134,  61,  95,  68, 126, 34, -11,
-      0,   0,   0,   0,   0,   0,  0,   0,
-    // Knight
-    -105, -21, -58, -33, -17, -28, -19,  -23,
-     -29, -53, -12,  -3,  -1,  18, -14,  -19,
-     -23,  -9,  12,  10,  19,  17,  25,  -16,
-     -13,   4,  16,  13,  28,  19,  21,   -8,
-      -9,  17,  19,  53,  37,  69,  18,   22,
-     -47,  60,  37,  65,  84, 129,  73,   44,
-     -73, -41,  72,  36,  23,  62,   7,  -17,
-    -167, -89, -34, -49,  61, -97, -15, -107,
-    // Bishop
-    -33,  -3, -14, -21, -13, -12, -39, -21,
-      4,  15,  16,   0,   7,  21,  33,   1,
-      0,  15,  15,  15,  14,  27,  18,  10,
-     -6,  13,  13,  26,  34,  12,  10,   4,
-     -4,   5,  19,  50,  37,  37,   7,  -2,
-    -16,  37,  43,  40,  35,  50,  37,  -2,
-    -26,  16, -18, -13,  30,  59,  18, -47,
-    -29,   4, -82, -37, -25, -42,   7,  -8,
-    // Rook
-    -19, -13,   1,  17, 16,  7, -37, -26,
-    -44, -16, -20,  -9, -1, 11,  -6, -71,
-    -45, -25, -16, -17,  3,  0,  -5, -33,
-    -36, -26, -12,  -1,  9, -7,   6, -23,
-    -24, -11,   7,  26, 24, 35,  -8, -20,
-     -5,  19,  26,  36, 17, 45,  61,  16,
-     27,  32,  58,  62, 80, 67,  26,  44,
-     32,  42,  32,  51, 63,  9,  31,  43,
-    // Queen
-     -1, -18,  -9,  10, -15, -25, -31, -50,
-    -35,  -8,  11,   2,   8,  15,  -3,   1,
-    -14,   2, -11,  -2,  -5,   2,  14,   5,
-     -9, -26,  -9, -10,  -2,  -4,   3,  -3,
-    -27, -27, -16, -16,  -1,  17,  -2,   1,
-    -13, -17,   7,   8,  29,  56,  47,  57,
-    -24, -39,  -5,   1, -16,  57,  28,  54,
-    -28,   0,  29,  12,  59,  44,  43,  45,
-    // King
-    -15,  36,  12, -54,   8, -28,  24,  14,
-      1,   7,  -8, -64, -43, -16,   9,   8,
-    -14, -14, -22, -46, -44, -30, -15, -27,
-    -49,  -1, -27, -39, -46, -44, -33, -51,
-    -17, -20, -12, -27, -30, -25, -14, -36,
-     -9,  24,   2, -16, -20,   6,  22, -22,
-     29,  -1, -20,  -7,  -8,  -4, -38, -29,
-    -65,  23,  16, -15, -56, -34,   2,  13,
-];
+/// Endgame scale that leaves the score as it is
+pub const SCALE_NORMAL: i16 = 64;
 
-const PIECE_SQUARE_TABLE_END: [i16; 64 * 6] = [
-    // Pawn
-      0,   0,   0,   0,   0,   0,   0,   0,
-     13,   8,   8,  10,  13,   0,   2,  -7,
-      4,   7,  -6,   1,   0,  -5,  -1,  -8,
-     13,   9,  -3,  -7,  -7,  -8,   3,  -1,
-     32,  24,  13,   5,  -2,   4,  17,  17,
-     94, 100,  85,  67,  56,  53,  82,  84,
-    178, 173, 158, 134, 147, 132, 165, 187,
-      0,   0,   0,   0,   0,   0,   0,   0,
-    // Knight
-    -29, -51, -23, -15, -22, -18, -50, -64,
-    -42, -20, -10,  -5,  -2, -20, -23, -44,
-    -23,  -3,  -1,  15,  10,  -3, -20, -22,
-    -18,  -6,  16,  25,  16,  17,   4, -18,
-    -17,   3,  22,  22,  22,  11,   8, -18,
-    -24, -20,  10,   9,  -1,  -9, -19, -41,
-    -25,  -8, -25,  -2,  -9, -25, -24, -52,
-    -58, -38, -13, -28, -31, -27, -63, -99,
-    // Bishop
-    -23,  -9, -23,  -5, -9, -16,  -5, -17,
-    -14, -18,  -7,  -1,  4,  -9, -15, -27,
-    -12,  -3,   8,  10, 13,   3,  -7, -15,
-     -6,   3,  13,  19,  7,  10,  -3,  -9,
-     -3,   9,  12,   9, 14,  10,   3,   2,
-      2,  -8,   0,  -1, -2,   6,   0,   4,
-     -8,  -4,   7, -12, -3, -13,  -4, -14,
-    -14, -21, -11,  -8, -7,  -9, -17, -24,
-    // Rook
-    -9,  2,  3, -1, -5, -13,   4, -20,
-    -6, -6,  0,  2, -9,  -9, -11,  -3,
-    -4,  0, -5, -1, -7, -12,  -8, -16,
-     3,  5,  8,  4, -5,  -6,  -8, -11,
-     4,  3, 13,  1,  2,   1,  -1,   2,
-     7,  7,  7,  5,  4,  -3,  -5,  -3,
-    11, 13, 13, 11, -3,   3,   8,   3,
-    13, 10, 18, 15, 12,  12,   8,   5,
-    // Queen
-    -33, -28, -22, -43,  -5, -32, -20, -41,
-    -22, -23, -30, -16, -16, -23, -36, -32,
-    -16, -27,  15,   6,   9,  17,  10,   5,
-    -18,  28,  19,  47,  31,  34,  39,  23,
-      3,  22,  24,  45,  57,  40,  57,  36,
-    -20,   6,   9,  49,  47,  35,  19,   9,
-    -17,  20,  32,  41,  58,  25,  30,   0,
-     -9,  22,  22,  27,  27,  19,  10,  20,
-    // King
-    -53, -34, -21, -11, -28, -14, -24, -43,
-    -27, -11,   4,  13,  14,   4,  -5, -17,
-    -19,  -3,  11,  21,  23,  16,   7,  -9,
-    -18,  -4,  21,  24,  27,  23,   9, -11,
-     -8,  22,  24,  27,  26,  33,  26,   3,
-     10,  17,  23,  15,  20,  45,  44,  13,
-    -12,  17,  14,  17,  17,  38,  23,  11,
-    -74, -35, -18, -18, -11,  15,   4, -17,
-];
+/// Uses of every weight by each color, and the endgame scale and mop up of an evaluation
+#[cfg(test)]
+struct Counts([[i16; NUM_WEIGHTS]; 2], i16, i16);
+
+#[cfg(test)]
+impl Counts {
+    fn new() -> Self {
+        Self([[0; NUM_WEIGHTS]; 2], SCALE_NORMAL, 0)
+    }
+}
+
+#[cfg(test)]
+impl Trace for Counts {
+    fn add(&mut self, index: usize, color: Color, count: i16) {
+        self.0[color.to_index()][index] += count;
+    }
+
+    fn endgame(&mut self, scale: i16, mop_up: i16) {
+        (self.1, self.2) = (scale, mop_up);
+    }
+}
+
+/// Uses of every weight by each color in the evaluation of `fen`
+#[cfg(test)]
+fn trace_counts(fen: &str) -> [[i16; NUM_WEIGHTS]; 2] {
+    use core::str::FromStr;
+
+    let mut counts = Counts::new();
+    evaluate_traced(crate::Game::from_str(fen).unwrap().board(), &mut counts);
+    counts.0
+}
+
+#[test]
+fn test_trace() {
+    use core::str::FromStr;
+
+    const FENS: &[&str] = &[
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "2r3k1/5ppp/8/8/8/8/5PPP/3R2K1 b - - 0 1",
+        "8/8/4k3/8/8/3K4/8/8 w - - 0 1",
+        "8/8/8/8/2k5/8/8/R3K3 b - - 0 1",
+        "1k6/8/8/8/8/8/P7/2B1K3 w - - 0 1",
+    ];
+
+    assert_eq!(evaluate_static(&crate::Game::default()), Eval(0));
+
+    for fen in FENS {
+        let game = crate::Game::from_str(fen).unwrap();
+        let board = game.board();
+        let mut counts = Counts::new();
+        let eval = evaluate_traced(board, &mut counts);
+        assert_eq!(eval, evaluate_static(&game), "{fen}");
+
+        // the trace alone is enough to get the evaluation back
+        let score = |color: Color| counts.0[color.to_index()].iter()
+            .zip(WEIGHTS.iter())
+            .fold(S::default(), |acc, (c, w)| acc + *w * *c);
+        let white_eval = combine(score(Color::White) - score(Color::Black), game_phase(board), counts.1, counts.2);
+        assert_eq!(if board.side_to_move() == Color::White { white_eval } else { -white_eval }, eval, "{fen}");
+    }
+}
+
+#[test]
+fn test_endgame() {
+    use core::str::FromStr;
+
+    let breakdown = |fen: &str| Breakdown::new(crate::Game::from_str(fen).unwrap().board());
+
+    const SCALES: &[(&str, i16)] = &[
+        ("8/8/4k3/8/8/3K4/8/5B2 w - - 0 1", 0),
+        ("8/8/4k3/8/8/3K4/8/4NN2 w - - 0 1", 0),
+        ("8/8/4k3/8/8/3K4/8/4RN2 w - - 0 1", SCALE_NORMAL),
+        ("8/8/4kb2/8/8/3K4/8/4R3 w - - 0 1", 16),
+        ("8/4kb2/4p3/8/3P4/3KB3/8/8 w - - 0 1", 24),
+        ("8/4kb2/4p3/8/3P4/3KB3/8/4R2r w - - 0 1", 48),
+        ("1k6/8/8/8/8/8/P7/2B1K3 w - - 0 1", 0),
+        ("1k6/8/8/8/8/8/P7/1B2K3 w - - 0 1", SCALE_NORMAL),
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", SCALE_NORMAL),
+    ];
+
+    for (fen, scale) in SCALES {
+        assert_eq!(breakdown(fen).scale, *scale, "{fen}");
+    }
+
+    // the bare king is pushed to the edge and to the corner of the bishop's color in KBNK
+    assert!(breakdown("k7/8/8/8/8/8/8/1R2K3 w - - 0 1").mop_up > breakdown("8/8/8/3k4/8/8/8/1R2K3 w - - 0 1").mop_up);
+    assert!(breakdown("8/8/8/3K4/8/8/8/r3k3 w - - 0 1").mop_up < 0);
+    assert!(breakdown("k7/8/8/8/8/8/8/1BN1K3 w - - 0 1").mop_up > breakdown("7k/8/8/8/8/8/8/1BN1K3 w - - 0 1").mop_up);
+    assert_eq!(breakdown("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").mop_up, 0);
+}
+
+#[test]
+fn test_breakdown() {
+    use core::str::FromStr;
+
+    let game = crate::Game::from_str("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 b kq - 0 1").unwrap();
+    let breakdown = Breakdown::new(game.board());
+    assert_eq!(breakdown.eval, evaluate_static(&game));
+
+    let material = |color: Color| breakdown.terms[0][color.to_index()];
+    assert_eq!(material(Color::White), WEIGHTS[MATERIAL] * 8 + WEIGHTS[MATERIAL + 1] * 2 + WEIGHTS[MATERIAL + 2] * 2 + WEIGHTS[MATERIAL + 3] * 2 + WEIGHTS[MATERIAL + 4]);
+    assert!(breakdown.to_string().contains("Rook on open file"));
+}
+
+#[test]
+fn test_pawn_structure() {
+    use core::str::FromStr;
+
+    let [white, black] = trace_counts("4k3/8/8/3p4/8/2P5/2P4P/4K3 w - - 0 1");
+    assert_eq!((white[DOUBLED_PAWN], white[ISOLATED_PAWN], white[PASSED_PAWN + 1]), (1, 3, 1));
+    assert_eq!((black[DOUBLED_PAWN], black[ISOLATED_PAWN]), (0, 1));
+    assert_eq!(white[PASSED_PAWN..PASSED_PAWN + 8].iter().sum::<i16>(), 1);
+    assert_eq!(black[PASSED_PAWN..PASSED_PAWN + 8].iter().sum::<i16>(), 0);
+
+    // the pawn table gives the same evaluation whether it hits or not
+    let mut table = PawnTable::new();
+    for fen in [
+        "4k3/8/8/3p4/8/2P5/2P4P/4K3 w - - 0 1",
+        "4k3/8/8/3p4/8/2P5/2P4P/4K3 b - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "4k3/8/8/3p4/8/2P5/2P4P/3K4 w - - 0 1",
+    ] {
+        let game = crate::Game::from_str(fen).unwrap();
+        let eval = evaluate_static(&game);
+        assert_eq!(evaluate_with(&game, Some(&mut table)), eval, "{fen}");
+        assert_eq!(evaluate_with(&game, Some(&mut table)), eval, "{fen}");
+    }
+}
+
+#[test]
+fn test_mobility() {
+    for side in trace_counts("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1") {
+        assert_eq!((side[KNIGHT_MOBILITY + 2], side[BISHOP_MOBILITY], side[ROOK_MOBILITY], side[QUEEN_MOBILITY]), (2, 2, 2, 1));
+        assert_eq!(side[KING_ATTACK..KING_ATTACK + KING_ATTACK_UNITS].iter().sum::<i16>(), 0);
+    }
+
+    // the queen attacks h7 and the knight attacks f7 and h7
+    let [white, _] = trace_counts("6k1/5ppp/8/6N1/7Q/8/5PPP/6K1 w - - 0 1");
+    assert_eq!(white[KING_ATTACK + 5 + 2 * 2], 1);
+}
+
+#[test]
+fn test_piece_placement() {
+    // knight outpost on d5, rook on the open b file and the seventh rank
+    let [white, black] = trace_counts("4r1k1/1R3ppp/8/3Np3/2P5/8/5PPP/2R3K1 w - - 0 1");
+    assert_eq!((white[OUTPOST], white[OUTPOST + 1]), (1, 0));
+    assert_eq!((white[ROOK_OPEN_FILE], white[ROOK_SEMI_OPEN_FILE], white[ROOK_SEVENTH], white[CONNECTED_ROOKS]), (1, 0, 1, 0));
+    assert_eq!((black[ROOK_OPEN_FILE], black[ROOK_SEMI_OPEN_FILE]), (0, 0));
+
+    // outposts start on the 4th rank
+    assert_eq!(trace_counts("4k3/8/8/8/3N4/2P5/8/4K3 w - - 0 1")[0][OUTPOST], 1);
+    assert_eq!(trace_counts("4k3/8/8/8/8/3N4/2P5/4K3 w - - 0 1")[0][OUTPOST], 0);
+
+    // bishop pair and connected rooks against a rook on a semi-open file
+    let [white, black] = trace_counts("3r2k1/5ppp/8/8/8/1B2B3/3P1PPP/R4RK1 w - - 0 1");
+    assert_eq!((white[BISHOP_PAIR], white[CONNECTED_ROOKS], white[ROOK_OPEN_FILE]), (1, 1, 1));
+    assert_eq!((black[BISHOP_PAIR], black[ROOK_SEMI_OPEN_FILE], black[ROOK_OPEN_FILE]), (0, 1, 0));
+}
+
+#[test]
+fn test_threats() {
+    // the e pawn forks an undefended knight and bishop
+    let [white, black] = trace_counts("4k3/8/8/3n1b2/4P3/8/8/4K3 w - - 0 1");
+    assert_eq!(&white[THREAT_BY_PAWN..THREAT_BY_PAWN + 4], &[1, 1, 0, 0]);
+    assert_eq!((white[HANGING], white[PAWN_PUSH_THREAT]), (2, 0));
+    assert_eq!(black[HANGING], 0);
+
+    // d4d5 is defended by the e pawn and would fork the knight and bishop
+    let [white, _] = trace_counts("4k3/8/2n1b3/8/3PP3/8/8/4K3 w - - 0 1");
+    assert_eq!(white[PAWN_PUSH_THREAT], 2);
+
+    // the knight attacks the rook and the queen
+    let [white, _] = trace_counts("4k3/8/3r1q2/8/4N3/8/8/4K3 w - - 0 1");
+    assert_eq!(&white[THREAT_BY_MINOR..THREAT_BY_MINOR + 2], &[1, 1]);
+}
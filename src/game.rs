@@ -1,18 +1,27 @@
 use core::str::FromStr;
+use crate::eval::{self, S};
 
 #[derive(Clone)]
 pub struct Game {
     board: chess::Board,
     fifty_move_counter: usize,
+    fullmove_number: usize,
+    /// Hashes of the positions before this one, only kept by [`Game::play_move`]
     hash_history: Vec<u64>,
+    /// Material and piece square table score of each color, see [`eval::psqt`]
+    psqt: [S; 2],
 }
 
 impl Game {
     pub fn new(board: chess::Board) -> Self {
+        let psqt = eval::psqt(&board);
+
         Self {
             board,
             fifty_move_counter: 0,
+            fullmove_number: 1,
             hash_history: vec![],
+            psqt,
         }
     }
 
@@ -22,6 +31,8 @@ impl Game {
         self.board().piece_on(mov.get_dest()).is_some()
     }
 
+    /// Make a move without remembering the current position, which is cheap enough to be done
+    /// in searches. Use [`play_move`](Self::play_move) for moves that are actually played.
     pub fn make_move(&self, mov: chess::ChessMove) -> Self {
         let mut fifty_move_counter = self.fifty_move_counter + 1;
 
@@ -32,41 +43,253 @@ impl Game {
             fifty_move_counter = 0;
         }
 
+        let changes = piece_changes(&self.board, mov);
+        let psqt = eval::update_psqt(self.psqt, &changes);
         let board = self.board.make_move_new(mov);
-        let mut hash_history = self.hash_history.clone();
-        hash_history.push(board.get_hash());
+        let fullmove_number = self.next_fullmove_number();
+
+        debug_assert_eq!(psqt, eval::psqt(&board), "{mov} from {}", self.get_fen());
 
-        Self { board, fifty_move_counter, hash_history }
+        Self { board, fifty_move_counter, fullmove_number, hash_history: Vec::new(), psqt }
     }
 
     pub fn make_null_move(&self) -> Option<Self> {
         let board = self.board.null_move()?;
         let fifty_move_counter = self.fifty_move_counter + 1;
-        let mut hash_history = self.hash_history.clone();
-        hash_history.push(board.get_hash());
+        let fullmove_number = self.next_fullmove_number();
 
-        Some(Self { board, fifty_move_counter, hash_history })
+        Some(Self {
+            board,
+            fifty_move_counter,
+            fullmove_number,
+            hash_history: Vec::new(),
+            psqt: self.psqt,
+        })
     }
 
-    pub fn can_declare_draw(&self) -> bool {
-        for h in self.hash_history.iter() {
-            if self.hash_history.iter().filter(|i| h == *i).count() >= 3 {
-                return true;
-            }
-        }
+    fn next_fullmove_number(&self) -> usize {
+        self.fullmove_number + (self.board.side_to_move() == chess::Color::Black) as usize
+    }
+
+    /// Play a move, remembering the current position for repetition detection
+    pub fn play_move(&mut self, mov: chess::ChessMove) {
+        let mut next = self.make_move(mov);
+        next.hash_history = core::mem::take(&mut self.hash_history);
+        next.hash_history.push(self.board.get_hash());
+
+        *self = next;
+    }
+
+    /// Material and piece square table score of each color, kept up to date by
+    /// [`make_move`](Self::make_move)
+    pub fn psqt(&self) -> [S; 2] { self.psqt }
+
+    /// Hashes of the positions played before this one, oldest first
+    pub fn hash_history(&self) -> &[u64] { &self.hash_history }
+
+    pub fn fifty_move_counter(&self) -> usize { self.fifty_move_counter }
+
+    pub fn fullmove_number(&self) -> usize { self.fullmove_number }
 
-        self.fifty_move_counter >= 100
+    /// Check for a draw by threefold repetition or the fifty move rule
+    pub fn can_declare_draw(&self) -> bool {
+        let hash = self.board.get_hash();
+
+        // only positions after the last irreversible move with the same side to move can repeat
+        self.fifty_move_counter >= 100 || self.hash_history.iter().rev()
+            .take(self.fifty_move_counter)
+            .skip(1)
+            .step_by(2)
+            .filter(|h| **h == hash)
+            .count() >= 2
     }
 
     pub fn history_len(&self) -> usize { self.hash_history.len() }
 
     pub fn get_fen(&self) -> String {
         let rfen = self.board().to_string();
-        format!(
-            "{} {} {}",
-            &rfen[..rfen.len() - 4], self.fifty_move_counter,
-            self.hash_history.len() / 2 + 1,
-        )
+        let position = rfen.split(' ').take(4).collect::<Vec<_>>().join(" ");
+
+        format!("{position} {} {}", self.fifty_move_counter, self.fullmove_number)
+    }
+
+    /// Parse a move in UCI coordinate notation, which has to be legal in this position
+    pub fn parse_uci(&self, mov: &str) -> Result<chess::ChessMove, MoveError> {
+        let malformed = || MoveError::Malformed(mov.to_string());
+        let bytes = mov.as_bytes();
+
+        if !(4..=5).contains(&bytes.len()) {
+            return Err(malformed());
+        }
+
+        let src = parse_square(&bytes[0..2]).ok_or_else(malformed)?;
+        let dst = parse_square(&bytes[2..4]).ok_or_else(malformed)?;
+        let promotion = match bytes.get(4) {
+            None => None,
+            Some(b'n') => Some(chess::Piece::Knight),
+            Some(b'b') => Some(chess::Piece::Bishop),
+            Some(b'r') => Some(chess::Piece::Rook),
+            Some(b'q') => Some(chess::Piece::Queen),
+            Some(_) => return Err(malformed()),
+        };
+
+        let mov = chess::ChessMove::new(src, dst, promotion);
+        if chess::MoveGen::new_legal(&self.board).any(|m| m == mov) {
+            Ok(mov)
+        } else {
+            Err(MoveError::Illegal(mov.to_string()))
+        }
+    }
+
+    /// Parse a legal move in either UCI coordinate notation or standard algebraic notation
+    pub fn parse_move(&self, mov: &str) -> Result<chess::ChessMove, MoveError> {
+        match self.parse_uci(mov) {
+            Err(MoveError::Malformed(_)) => self.parse_san(mov).ok_or_else(|| MoveError::Illegal(mov.to_string())),
+            result => result,
+        }
+    }
+
+    /// Format a legal move in standard algebraic notation
+    pub fn san(&self, mov: chess::ChessMove) -> String {
+        let board = self.board();
+        let src = mov.get_source();
+        let dst = mov.get_dest();
+        let piece = board.piece_on(src).unwrap_or(chess::Piece::Pawn);
+        let file_char = |sq: chess::Square| (b'a' + sq.get_file().to_index() as u8) as char;
+
+        let mut san = if piece == chess::Piece::King && src.get_file().to_index().abs_diff(dst.get_file().to_index()) == 2 {
+            if dst.get_file() == chess::File::G { "O-O".to_string() } else { "O-O-O".to_string() }
+        } else if piece == chess::Piece::Pawn {
+            let mut san = String::new();
+            if src.get_file() != dst.get_file() {
+                san.push(file_char(src));
+                san.push('x');
+            }
+            san += &dst.to_string();
+
+            if let Some(promotion) = mov.get_promotion() {
+                san.push('=');
+                san += &promotion.to_string(chess::Color::White);
+            }
+
+            san
+        } else {
+            let mut san = piece.to_string(chess::Color::White);
+
+            let others = chess::MoveGen::new_legal(board)
+                .filter(|m| m.get_dest() == dst && m.get_source() != src && board.piece_on(m.get_source()) == Some(piece))
+                .collect::<Vec<_>>();
+            if !others.is_empty() {
+                let file_unique = others.iter().all(|m| m.get_source().get_file() != src.get_file());
+                let rank_unique = others.iter().all(|m| m.get_source().get_rank() != src.get_rank());
+
+                if file_unique || !rank_unique {
+                    san.push(file_char(src));
+                }
+                if !file_unique {
+                    san.push((b'1' + src.get_rank().to_index() as u8) as char);
+                }
+            }
+
+            if self.is_capture(mov) {
+                san.push('x');
+            }
+            san += &dst.to_string();
+
+            san
+        };
+
+        let next = board.make_move_new(mov);
+        if next.checkers().0 != 0 {
+            san.push(if chess::MoveGen::new_legal(&next).len() == 0 { '#' } else { '+' });
+        }
+
+        san
+    }
+
+    /// Format a sequence of moves played from this position in standard algebraic notation
+    pub fn san_line(&self, moves: &[chess::ChessMove]) -> String {
+        let mut game = self.clone();
+
+        moves.iter().map(|m| {
+            let san = game.san(*m);
+            game = game.make_move(*m);
+            san
+        }).collect::<Vec<_>>().join(" ")
+    }
+
+    /// Parse a move in standard algebraic notation, which has to be legal and unambiguous
+    pub fn parse_san(&self, san: &str) -> Option<chess::ChessMove> {
+        let board = self.board();
+        let san = san.trim_end_matches(['+', '#', '!', '?']);
+
+        if matches!(san, "O-O" | "0-0" | "O-O-O" | "0-0-0") {
+            let king = board.king_square(board.side_to_move());
+            let file = if san.len() == 3 { chess::File::G } else { chess::File::C };
+            let mov = chess::ChessMove::new(king, chess::Square::make_square(king.get_rank(), file), None);
+
+            return chess::MoveGen::new_legal(board).any(|m| m == mov).then_some(mov);
+        }
+
+        let mut chars = san.as_bytes();
+        let piece = match chars.first()? {
+            b'N' => chess::Piece::Knight,
+            b'B' => chess::Piece::Bishop,
+            b'R' => chess::Piece::Rook,
+            b'Q' => chess::Piece::Queen,
+            b'K' => chess::Piece::King,
+            _ => chess::Piece::Pawn,
+        };
+        if piece != chess::Piece::Pawn {
+            chars = &chars[1..];
+        }
+
+        let mut promotion = None;
+        if let Some((last, rest)) = chars.split_last() {
+            promotion = match last {
+                b'N' => Some(chess::Piece::Knight),
+                b'B' => Some(chess::Piece::Bishop),
+                b'R' => Some(chess::Piece::Rook),
+                b'Q' => Some(chess::Piece::Queen),
+                _ => None,
+            };
+
+            if promotion.is_some() {
+                chars = rest.strip_suffix(b"=").unwrap_or(rest);
+            }
+        }
+
+        if chars.len() < 2 { return None };
+        let (rest, dst) = chars.split_at(chars.len() - 2);
+        let dst = parse_square(dst)?;
+        let rest = rest.strip_suffix(b"x").unwrap_or(rest);
+
+        let mut src_file = None;
+        let mut src_rank = None;
+        for c in rest {
+            match c {
+                b'a'..=b'h' => src_file = Some(chess::File::from_index((c - b'a') as usize)),
+                b'1'..=b'8' => src_rank = Some(chess::Rank::from_index((c - b'1') as usize)),
+                _ => return None,
+            }
+        }
+
+        let mut found = None;
+        for m in chess::MoveGen::new_legal(board) {
+            let src = m.get_source();
+
+            if m.get_dest() == dst
+                && m.get_promotion() == promotion
+                && board.piece_on(src) == Some(piece)
+                && src_file.is_none_or(|f| src.get_file() == f)
+                && src_rank.is_none_or(|r| src.get_rank() == r)
+            {
+                if found.is_some() { return None };
+                found = Some(m);
+            }
+        }
+
+        found
     }
 
     pub fn visualize(&self, bitboard: chess::BitBoard) {
@@ -151,7 +374,7 @@ impl core::fmt::Display for Game {
         writeln!(f, "Hash: 0x{:016x}", self.board().get_hash())?;
         writeln!(f)?;
 
-        let phase = crate::eval::game_phase(self.board()) as usize;
+        let phase = eval::game_phase(self.board()) as usize;
         writeln!(f, "Phase: {0:█<1$}{0:░<phase$} end", "", 24 - phase)?;
 
         Ok(())
@@ -159,23 +382,290 @@ impl core::fmt::Display for Game {
 }
 
 impl FromStr for Game {
-    type Err = Box<dyn core::error::Error>;
+    type Err = FenError;
 
+    /// Parse a FEN. The halfmove clock and fullmove number can be left out, in which case they
+    /// are 0 and 1 like in EPD.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (rest, moves) = s.rsplit_once(' ').ok_or("")?;
-        let (_, fmc) = rest.rsplit_once(' ').ok_or("")?;
-        let board = chess::Board::from_str(s).map_err(|_| "")?;
+        let fields = s.split_whitespace().collect::<Vec<_>>();
+
+        const NAMES: [&str; 4] = ["piece placement", "side to move", "castling rights", "en passant square"];
+        if let Some(name) = NAMES.get(fields.len()) {
+            return Err(FenError::MissingField(name));
+        }
+        if fields.len() > 6 {
+            return Err(FenError::TrailingFields(fields[6..].join(" ")));
+        }
+
+        let board = chess::Board::from_str(&fields[..4].join(" "))
+            .map_err(|_| FenError::InvalidPosition(fields[..4].join(" ")))?;
+        let fifty_move_counter = fields.get(4).map_or(Ok(0), |f| f.parse::<usize>())
+            .map_err(|_| FenError::InvalidHalfmoveClock(fields[4].to_string()))?;
+        let fullmove_number = fields.get(5).map_or(Ok(1), |f| f.parse::<usize>())
+            .ok()
+            .filter(|n| *n != 0)
+            .ok_or_else(|| FenError::InvalidFullmoveNumber(fields[5].to_string()))?;
+
+        let psqt = eval::psqt(&board);
 
         Ok(Self {
             board,
-            fifty_move_counter: fmc.parse()?,
-            hash_history: (0..moves.parse::<u64>()? * 2 - (board.side_to_move() == chess::Color::White) as u64).collect(),
+            fifty_move_counter,
+            fullmove_number,
+            hash_history: Vec::new(),
+            psqt,
         })
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    MissingField(&'static str),
+    TrailingFields(String),
+    InvalidPosition(String),
+    InvalidHalfmoveClock(String),
+    InvalidFullmoveNumber(String),
+}
+
+impl core::fmt::Display for FenError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::MissingField(name) => write!(f, "FEN is missing the {name}"),
+            Self::TrailingFields(rest) => write!(f, "unexpected `{rest}` at the end of FEN"),
+            Self::InvalidPosition(pos) => write!(f, "`{pos}` is not a valid position"),
+            Self::InvalidHalfmoveClock(hmc) => write!(f, "`{hmc}` is not a valid halfmove clock"),
+            Self::InvalidFullmoveNumber(fmn) => write!(f, "`{fmn}` is not a valid fullmove number"),
+        }
+    }
+}
+
+impl core::error::Error for FenError {}
+
+/// A piece appearing on or disappearing from a square
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PieceChange {
+    pub piece: chess::Piece,
+    pub color: chess::Color,
+    pub square: chess::Square,
+    pub add: bool,
+}
+
+/// Get the pieces a legal move adds and removes, including captured pieces and castling rooks
+pub fn piece_changes(board: &chess::Board, mov: chess::ChessMove) -> arrayvec::ArrayVec<PieceChange, 4> {
+    let mut changes = arrayvec::ArrayVec::new();
+    let src = mov.get_source();
+    let dst = mov.get_dest();
+    let color = board.side_to_move();
+    let piece = board.piece_on(src).unwrap_or(chess::Piece::Pawn);
+
+    changes.push(PieceChange { piece, color, square: src, add: false });
+    changes.push(PieceChange { piece: mov.get_promotion().unwrap_or(piece), color, square: dst, add: true });
+
+    if let Some(captured) = board.piece_on(dst) {
+        changes.push(PieceChange { piece: captured, color: !color, square: dst, add: false });
+    } else if piece == chess::Piece::Pawn && src.get_file() != dst.get_file() {
+        let square = chess::Square::make_square(src.get_rank(), dst.get_file());
+        changes.push(PieceChange { piece: chess::Piece::Pawn, color: !color, square, add: false });
+    }
+
+    if piece == chess::Piece::King && src.get_file().to_index().abs_diff(dst.get_file().to_index()) == 2 {
+        let (from, to) = if dst.get_file() == chess::File::G {
+            (chess::File::H, chess::File::F)
+        } else {
+            (chess::File::A, chess::File::D)
+        };
+
+        changes.push(PieceChange { piece: chess::Piece::Rook, color, square: chess::Square::make_square(src.get_rank(), from), add: false });
+        changes.push(PieceChange { piece: chess::Piece::Rook, color, square: chess::Square::make_square(src.get_rank(), to), add: true });
+    }
+
+    changes
+}
+
+fn parse_square(s: &[u8]) -> Option<chess::Square> {
+    match s {
+        [file @ b'a'..=b'h', rank @ b'1'..=b'8'] => Some(chess::Square::make_square(
+            chess::Rank::from_index((rank - b'1') as usize),
+            chess::File::from_index((file - b'a') as usize),
+        )),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoveError {
+    Malformed(String),
+    Illegal(String),
+}
+
+impl core::fmt::Display for MoveError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Malformed(mov) => write!(f, "`{mov}` is not a valid move"),
+            Self::Illegal(mov) => write!(f, "`{mov}` is not a legal move"),
+        }
+    }
+}
+
+impl core::error::Error for MoveError {}
+
 impl Default for Game {
     fn default() -> Self {
         Self::new(chess::Board::default())
     }
 }
+
+#[test]
+fn test_repetition() {
+    let mut game = Game::default();
+    for m in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+        game.play_move(game.parse_uci(m).unwrap());
+    }
+    assert!(!game.can_declare_draw());
+
+    for m in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+        game.play_move(game.parse_uci(m).unwrap());
+    }
+    assert!(game.can_declare_draw());
+
+    // the pawn move makes the earlier positions unreachable
+    for m in ["e2e4", "g8f6", "g1f3", "f6g8", "f3g1", "g8f6", "g1f3", "f6g8"] {
+        game.play_move(game.parse_uci(m).unwrap());
+        assert!(!game.can_declare_draw());
+    }
+}
+
+#[test]
+fn test_fen() {
+    const FENS: &[&str] = &[
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "4rrk1/pp1n3p/3q2pQ/2p1pb2/2PP4/2P3N1/P2B2PP/4RRK1 b - - 7 19",
+        "5rk1/q6p/2p3bR/1pPp1rP1/1P1Pp3/P3B1Q1/1K3P2/R7 w - - 93 90",
+        "8/R7/2q5/8/6k1/8/1P5p/K6R w - - 0 124",
+        "r3k2r/8/3Q4/8/8/5q2/8/R3K2R b KQkq - 0 1",
+        "4k3/8/8/8/8/8/8/4K2R w K - 0 1",
+        "r3k3/8/8/8/8/8/8/4K3 b q - 12 40",
+    ];
+
+    for fen in FENS {
+        let game = Game::from_str(fen).unwrap();
+        assert_eq!(game.get_fen(), *fen);
+
+        let round_trip = Game::from_str(&game.get_fen()).unwrap();
+        assert!(round_trip.board() == game.board(), "{fen}");
+        assert_eq!(round_trip.fifty_move_counter(), game.fifty_move_counter());
+        assert_eq!(round_trip.fullmove_number(), game.fullmove_number());
+    }
+
+    let mut game = Game::default();
+    game.play_move(chess::ChessMove::new(chess::Square::make_square(chess::Rank::Second, chess::File::E), chess::Square::make_square(chess::Rank::Fourth, chess::File::E), None));
+    assert_eq!(game.get_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1");
+    game.play_move(chess::ChessMove::new(chess::Square::make_square(chess::Rank::Eighth, chess::File::G), chess::Square::make_square(chess::Rank::Sixth, chess::File::F), None));
+    assert_eq!(game.get_fen(), "rnbqkb1r/pppppppp/5n2/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 1 2");
+
+    assert_eq!(Game::from_str("8/8/8/8/8/8/8/8 w").err(), Some(FenError::MissingField("castling rights")));
+    assert_eq!(Game::from_str("").err(), Some(FenError::MissingField("piece placement")));
+    assert!(matches!(Game::from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - x 1"), Err(FenError::InvalidHalfmoveClock(_))));
+    assert!(matches!(Game::from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 0"), Err(FenError::InvalidFullmoveNumber(_))));
+    assert!(matches!(Game::from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 2"), Err(FenError::TrailingFields(_))));
+    assert!(matches!(Game::from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1"), Err(FenError::InvalidPosition(_))));
+}
+
+#[test]
+fn test_san() {
+    const MOVES: &[(&str, &str)] = &[
+        ("4k3/8/8/8/8/2N3N1/8/4K3 w - - 0 1", "Nce4"),
+        ("4k3/8/8/8/2N5/8/2N5/4K3 w - - 0 1", "N2e3"),
+        ("4k3/8/8/8/2N5/8/2N3N1/4K3 w - - 0 1", "Nc2e3"),
+        ("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b8=Q+"),
+        ("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b8=N"),
+        ("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "Ra8#"),
+        ("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1", "O-O-O"),
+        ("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "O-O"),
+        ("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3", "exf6"),
+        ("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2", "Nf3"),
+    ];
+
+    for (fen, expected) in MOVES {
+        let game = Game::from_str(fen).unwrap();
+        let mov = game.parse_san(expected).unwrap();
+
+        assert_eq!(game.san(mov), *expected);
+    }
+
+    let game = Game::from_str("4k3/8/8/8/8/2N3N1/8/4K3 w - - 0 1").unwrap();
+    assert_eq!(game.parse_san("Ne4"), None);
+    assert_eq!(game.parse_san("Ne5"), None);
+    assert_eq!(game.parse_san("Ncxe4"), game.parse_san("Nce4"));
+    assert_eq!(game.parse_san(""), None);
+
+    let game = Game::default();
+    let moves = ["e4", "e5", "Nf3", "Nc6", "Bb5"].iter().scan(game.clone(), |g, san| {
+        let m = g.parse_san(san)?;
+        *g = g.make_move(m);
+        Some(m)
+    }).collect::<Vec<_>>();
+    assert_eq!(game.san_line(&moves), "e4 e5 Nf3 Nc6 Bb5");
+}
+
+#[test]
+fn test_parse_move() {
+    let game = Game::default();
+    assert_eq!(game.parse_uci("g1f3").map(|m| m.to_string()), Ok("g1f3".to_string()));
+    assert_eq!(game.parse_move("Nf3"), game.parse_uci("g1f3"));
+    assert_eq!(game.parse_uci("e2e5"), Err(MoveError::Illegal("e2e5".to_string())));
+    assert_eq!(game.parse_uci("e2"), Err(MoveError::Malformed("e2".to_string())));
+    assert_eq!(game.parse_uci("e2e4k"), Err(MoveError::Malformed("e2e4k".to_string())));
+    assert_eq!(game.parse_uci("i2e4"), Err(MoveError::Malformed("i2e4".to_string())));
+    assert_eq!(game.parse_move("Nf4"), Err(MoveError::Illegal("Nf4".to_string())));
+
+    let game = Game::from_str("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+    assert!(game.parse_uci("b7b8r").is_ok());
+    assert!(matches!(game.parse_uci("b7b8"), Err(MoveError::Illegal(_))));
+}
+
+/// Replay lines with castling, promotions, captures and en passant for testing incremental
+/// updates. `start` makes a state from the first position of a line, which `step` gets along
+/// with the positions before and after every move.
+#[cfg(test)]
+pub(crate) fn replay_test_lines<T>(
+    mut start: impl FnMut(&Game) -> T,
+    mut step: impl FnMut(&mut T, &Game, chess::ChessMove, &Game),
+) {
+    const LINES: &[(&str, &[&str])] = &[
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", &["e2e4", "d7d5", "e4d5", "d8d5", "b1c3", "d5a5", "d2d4", "c7c6", "g1f3", "c8g4", "f1e2", "e7e6", "e1g1", "b8d7", "c1e3", "e8c8"]),
+        ("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", &["c4c5", "c7c5", "b5c6", "e8c8", "a7a8q", "c8c7", "d1e1", "b2a1n"]),
+        ("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3", &["e5f6", "g8f6"]),
+    ];
+
+    for (fen, moves) in LINES {
+        let mut game = Game::from_str(fen).unwrap();
+        let mut state = start(&game);
+
+        for m in moves.iter() {
+            let mov = game.parse_uci(m).unwrap();
+            let next = game.make_move(mov);
+            step(&mut state, &game, mov, &next);
+            game = next;
+        }
+    }
+}
+
+#[test]
+fn test_psqt() {
+    replay_test_lines(
+        |game| assert_eq!(game.psqt(), eval::psqt(game.board()), "{}", game.get_fen()),
+        |_, game, mov, next| {
+            assert_eq!(next.psqt(), eval::psqt(next.board()), "{mov} from {}", game.get_fen());
+
+            if let Some(null) = next.make_null_move() {
+                assert_eq!(null.psqt(), eval::psqt(null.board()), "null move after {mov} from {}", game.get_fen());
+            }
+        },
+    );
+}
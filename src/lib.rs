@@ -1,23 +1,30 @@
-pub use eval::{Eval, evaluate_static};
+pub use eval::{Eval, evaluate, evaluate_static};
 pub use game::Game;
+pub use perft::{perft, perft_divide};
 pub use see::see;
 
 use std::time::*;
-use std::sync::atomic::*;
+use std::sync::{atomic::*, Arc};
 
 use sync::*;
 
 use parking_lot::{Condvar, Mutex, RwLock};
 
+pub mod book;
 mod debug;
-mod eval;
+pub mod eval;
 pub mod game;
 mod move_order;
+pub mod nnue;
 mod node;
+mod pawn_table;
+mod perft;
+pub mod pgn;
 mod search;
 mod see;
 mod shared_table;
 mod sync;
+pub mod syzygy;
 mod trans_table;
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -30,6 +37,7 @@ pub struct Engine {
     soft_time_bound: Duration,
     hard_time_bound: Duration,
     can_time_out: AtomicBool,
+    signals: Arc<SearchSignals>,
 
     debug: debug::DebugStats,
 
@@ -40,6 +48,16 @@ pub struct Engine {
     total_nodes_searched: AtomicUsize,
 
     smp_count: usize,
+
+    multi_pv: usize,
+    pv_lines: Mutex<Vec<(chess::ChessMove, Eval)>>,
+    node_limit: usize,
+    root_moves: Vec<chess::ChessMove>,
+
+    tablebase: Option<syzygy::Tablebase>,
+    /// The root move picked by the tablebases, which is the only one searched if there is one
+    tb_root_move: Option<chess::ChessMove>,
+    tb_hits: AtomicUsize,
 }
 
 pub(crate) struct SmpThread<'a, const MAIN: bool = false> {
@@ -49,11 +67,32 @@ pub(crate) struct SmpThread<'a, const MAIN: bool = false> {
     hist_table: move_order::HistoryTable,
     countermove: move_order::CountermoveTable,
 
+    /// Root moves skipped by the current root search, i.e. the ones already found by earlier
+    /// MultiPV lines
+    root_excluded: Vec<chess::ChessMove>,
+
+    /// Hashes of the positions played before the root, followed by the ones of the current
+    /// search path indexed by ply
+    hash_stack: Vec<u64>,
+    root_ply: usize,
+    /// Ply of the position after the last null move on the current search path, repetitions
+    /// can't reach past it
+    null_ply: Option<usize>,
+
+    pawn_table: pawn_table::PawnTable,
+
+    /// The network taken at the start of the current root search, if there is one
+    network: Option<Arc<nnue::Network>>,
+    /// Accumulators of the current search path indexed by ply, only kept with a network
+    acc_stack: Vec<nnue::Accumulator>,
+
     nodes_searched: usize,
 }
 
 impl Engine {
     pub fn new(game: Game, hash_size_bytes: usize) -> Self {
+        nnue::init();
+
         Self {
             game: RwLock::new(game),
             trans_table: trans_table::TransTable::new(hash_size_bytes / trans_table::TransTable::entry_size()),
@@ -62,6 +101,7 @@ impl Engine {
             soft_time_bound: Duration::default(),
             hard_time_bound: Duration::default(),
             can_time_out: AtomicBool::new(true),
+            signals: Arc::new(SearchSignals::default()),
 
             debug: debug::DebugStats::default(),
 
@@ -72,6 +112,15 @@ impl Engine {
             total_nodes_searched: AtomicUsize::new(0),
 
             smp_count: 0,
+
+            multi_pv: 1,
+            pv_lines: Mutex::new(Vec::new()),
+            node_limit: usize::MAX,
+            root_moves: Vec::new(),
+
+            tablebase: None,
+            tb_root_move: None,
+            tb_hits: AtomicUsize::new(0),
         }
     }
 
@@ -100,6 +149,17 @@ impl Engine {
             hist_table: move_order::ButterflyTable::new(),
             countermove: move_order::CountermoveTable::new(),
 
+            root_excluded: Vec::new(),
+
+            hash_stack: Vec::new(),
+            root_ply: 0,
+            null_ply: None,
+
+            pawn_table: pawn_table::PawnTable::new(),
+
+            network: None,
+            acc_stack: Vec::new(),
+
             nodes_searched: 0,
         }
     }
@@ -134,12 +194,57 @@ impl Engine {
         self.hard_time_bound = time;
     }
 
+    /// Let the following searches be stopped by time, node limits and [`SearchSignals::stop`],
+    /// or only by the depth they are searched to if `false`, which keeps them deterministic
+    pub fn set_can_time_out(&mut self, can_time_out: bool) {
+        self.can_time_out.store(can_time_out, Ordering::Relaxed);
+    }
+
+    /// Stop the following searches after about `nodes` nodes, or never if `None`
+    pub fn limit_nodes(&mut self, nodes: Option<usize>) {
+        self.node_limit = nodes.unwrap_or(usize::MAX);
+    }
+
+    /// Only consider `moves` at the root in the following searches, or every legal move if it is
+    /// empty
+    pub fn restrict_root_moves(&mut self, moves: Vec<chess::ChessMove>) {
+        self.root_moves = moves;
+    }
+
+    pub fn set_tablebase(&mut self, tablebase: Option<syzygy::Tablebase>) {
+        self.tablebase = tablebase;
+    }
+
+    /// Remove the tablebases from the following searches, giving them back so that they can be
+    /// set again later
+    pub fn take_tablebase(&mut self) -> Option<syzygy::Tablebase> {
+        self.tablebase.take()
+    }
+
+    /// Pick the root move with the tablebases if the root position is in them and the move is
+    /// allowed by [`restrict_root_moves`](Self::restrict_root_moves)
+    fn probe_tb_root(&self) -> Option<chess::ChessMove> {
+        let (mov, _) = self.tablebase.as_ref()?.probe_root(&self.game.read())?;
+
+        (self.root_moves.is_empty() || self.root_moves.contains(&mov)).then_some(mov)
+    }
+
     pub fn soft_times_up(&self) -> bool {
-        self.can_time_out.load(Ordering::Relaxed) && self.elapsed() > self.soft_time_bound
+        self.can_time_out.load(Ordering::Relaxed) && (self.signals.stopped() || self.time_bound_passed(self.soft_time_bound))
     }
 
     pub fn hard_times_up(&self) -> bool {
-        self.can_time_out.load(Ordering::Relaxed) && self.elapsed() > self.hard_time_bound
+        self.can_time_out.load(Ordering::Relaxed) && (self.signals.stopped() || self.time_bound_passed(self.hard_time_bound))
+    }
+
+    fn time_bound_passed(&self, bound: Duration) -> bool {
+        !self.signals.pondering() && self.signals.clock() > bound
+    }
+
+    /// Get a handle to the signals of this engine, which can be used to stop a search or end
+    /// pondering from another thread
+    pub fn signals(&self) -> Arc<SearchSignals> {
+        Arc::clone(&self.signals)
     }
 
     pub fn find_pv(&self, best: chess::ChessMove, max: usize) -> Vec<chess::ChessMove> {
@@ -161,10 +266,33 @@ impl Engine {
         pv
     }
 
+    /// Set the amount of best root moves to search for in the following searches
+    pub fn set_multi_pv(&mut self, lines: usize) {
+        self.multi_pv = lines.max(1);
+    }
+
+    pub fn multi_pv(&self) -> usize { self.multi_pv }
+
+    /// Get the root moves found by the last completed iteration along with their evaluations,
+    /// best first. There are as many of them as requested by [`set_multi_pv`](Self::set_multi_pv)
+    /// unless there aren't enough legal moves.
+    pub fn pv_lines(&self) -> Vec<(chess::ChessMove, Eval)> {
+        self.pv_lines.lock().clone()
+    }
+
     pub fn nodes(&self) -> usize {
         self.total_nodes_searched.load(Ordering::Relaxed)
     }
 
+    pub fn tb_hits(&self) -> usize {
+        self.tb_hits.load(Ordering::Relaxed)
+    }
+
+    /// Search statistics of the last search, for debugging
+    pub fn debug_stats(&self) -> &impl core::fmt::Debug {
+        &self.debug
+    }
+
     pub fn elapsed(&self) -> Duration {
         self.time_ref.elapsed()
     }
@@ -175,8 +303,11 @@ impl Engine {
         self.trans_table.filter_count(|e| e.node_type() != node::NodeType::None)
     }
 
-    pub fn resize_hash(&mut self, hash_size_bytes: usize) {
-        self.trans_table = trans_table::TransTable::new(hash_size_bytes / trans_table::TransTable::entry_size());
+    /// Replace the transposition table with an empty one of about `hash_size_bytes`, keeping the
+    /// current one if there isn't enough memory
+    pub fn resize_hash(&mut self, hash_size_bytes: usize) -> Result<(), std::collections::TryReserveError> {
+        self.trans_table = trans_table::TransTable::try_new(hash_size_bytes / trans_table::TransTable::entry_size())?;
+        Ok(())
     }
 
     pub fn clear_hash(&mut self) {
@@ -211,3 +342,86 @@ pub struct TimeControl {
     pub time_left: usize,
     pub time_incr: usize,
 }
+
+const SEARCH_IDLE: u8 = 0;
+const SEARCH_RUNNING: u8 = 1;
+const SEARCH_STOPPING: u8 = 2;
+
+/// Signals shared between a search and the threads controlling it
+#[derive(Debug)]
+pub struct SearchSignals {
+    state: AtomicU8,
+    pondering: AtomicBool,
+
+    epoch: Instant,
+    /// Nanoseconds after `epoch` at which the time bounds started counting
+    clock_start: AtomicU64,
+}
+
+impl Default for SearchSignals {
+    fn default() -> Self {
+        Self {
+            state: AtomicU8::new(SEARCH_IDLE),
+            pondering: AtomicBool::new(false),
+
+            epoch: Instant::now(),
+            clock_start: AtomicU64::new(0),
+        }
+    }
+}
+
+impl SearchSignals {
+    /// Mark a search as running, which ignores the time bounds until [`ponderhit`](Self::ponderhit)
+    /// if `ponder` is set. Input threads should call this before passing a `go` on, so that a
+    /// `stop` or `ponderhit` coming right after it is not lost.
+    pub fn start(&self, ponder: bool) {
+        self.pondering.store(ponder, Ordering::Relaxed);
+        self.begin();
+    }
+
+    /// Mark a search as running without changing whether it is pondering
+    pub(crate) fn begin(&self) {
+        let _ = self.state.compare_exchange(SEARCH_IDLE, SEARCH_RUNNING, Ordering::Relaxed, Ordering::Relaxed);
+        self.restart_clock();
+    }
+
+    /// Ask the running search to return as soon as possible. Does nothing if no search is running.
+    pub fn stop(&self) {
+        let _ = self.state.compare_exchange(SEARCH_RUNNING, SEARCH_STOPPING, Ordering::Relaxed, Ordering::Relaxed);
+    }
+
+    /// Turn a pondering search into a normal one, with the time bounds counting from now
+    pub fn ponderhit(&self) {
+        if self.pondering() {
+            self.restart_clock();
+            self.pondering.store(false, Ordering::Relaxed);
+        }
+    }
+
+    /// Mark the search as finished, after which [`stop`](Self::stop) does nothing until the next
+    /// search is started.
+    pub fn finish(&self) {
+        self.state.store(SEARCH_IDLE, Ordering::Relaxed);
+    }
+
+    pub fn searching(&self) -> bool {
+        self.state.load(Ordering::Relaxed) != SEARCH_IDLE
+    }
+
+    pub fn stopped(&self) -> bool {
+        self.state.load(Ordering::Relaxed) == SEARCH_STOPPING
+    }
+
+    pub fn pondering(&self) -> bool {
+        self.pondering.load(Ordering::Relaxed)
+    }
+
+    /// Time since the time bounds started counting
+    pub fn clock(&self) -> Duration {
+        self.epoch.elapsed().saturating_sub(Duration::from_nanos(self.clock_start.load(Ordering::Relaxed)))
+    }
+
+    fn restart_clock(&self) {
+        self.clock_start.store(self.epoch.elapsed().as_nanos() as u64, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,204 @@
+use std::{io, path::Path, sync::Arc};
+use arc_swap::ArcSwapOption;
+use chess::{Board, Color, Piece, Square};
+use crate::{Eval, game::PieceChange};
+
+/// Size of the hidden layer, which is the accumulator of one perspective
+pub const HIDDEN: usize = 256;
+const INPUTS: usize = 768;
+
+/// Quantization of the feature transformer
+const QA: i32 = 255;
+/// Quantization of the output layer
+const QB: i32 = 64;
+/// Scale of the network output to centipawns
+const SCALE: i32 = 400;
+
+/// The currently used network, which is [`None`] when the handcrafted evaluation is used.
+/// Replaced networks are freed once nothing from [`network`] holds them anymore.
+static NETWORK: ArcSwapOption<Network> = ArcSwapOption::const_empty();
+
+/// A (768→[`HIDDEN`])x2→1 network with a clipped ReLU activation
+pub struct Network {
+    feature_weights: Vec<[i16; HIDDEN]>,
+    feature_bias: [i16; HIDDEN],
+    /// Weights for the accumulator of the side to move, then the other side
+    output_weights: [[i16; HIDDEN]; 2],
+    output_bias: i16,
+}
+
+/// The hidden layer of both perspectives, indexed by color
+#[derive(Clone)]
+#[repr(C, align(64))]
+pub struct Accumulator {
+    vals: [[i16; HIDDEN]; 2],
+}
+
+impl Network {
+    /// Size of a network file without the padding at the end
+    const SIZE: usize = (INPUTS * HIDDEN + HIDDEN + 2 * HIDDEN + 1) * 2;
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// Read a network of little endian `i16`s in the order of feature weights, feature biases,
+    /// output weights then the output bias, which may be padded to a multiple of 64 bytes
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        if !(Self::SIZE..Self::SIZE + 64).contains(&bytes.len()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("network is {} bytes, expected {} bytes", bytes.len(), Self::SIZE),
+            ));
+        }
+
+        let mut values = bytes.chunks_exact(2).map(|b| i16::from_le_bytes([b[0], b[1]]));
+        let mut layer = || core::array::from_fn::<_, HIDDEN, _>(|_| values.next().unwrap_or(0));
+
+        let feature_weights = (0..INPUTS).map(|_| layer()).collect();
+        let feature_bias = layer();
+        let output_weights = [layer(), layer()];
+        let output_bias = values.next().unwrap_or(0);
+
+        Ok(Self { feature_weights, feature_bias, output_weights, output_bias })
+    }
+
+    /// Evaluate a position from the point of view of the side to move
+    pub fn evaluate(&self, acc: &Accumulator, stm: Color) -> Eval {
+        let us = &acc.vals[stm.to_index()];
+        let them = &acc.vals[(!stm).to_index()];
+
+        let mut sum = 0;
+        for (vals, weights) in [(us, &self.output_weights[0]), (them, &self.output_weights[1])] {
+            for (v, w) in vals.iter().zip(weights.iter()) {
+                sum += (*v as i32).clamp(0, QA) * *w as i32;
+            }
+        }
+
+        let eval = (sum + self.output_bias as i32) * SCALE / (QA * QB);
+
+        // stay out of the ranges of tablebase and mate scores
+        Eval(eval.clamp(-Eval::TB_WIN.0 as i32 + 1, Eval::TB_WIN.0 as i32 - 1) as i16)
+    }
+}
+
+/// Index of a piece in the input layer from the point of view of `perspective`
+#[inline]
+fn feature(perspective: Color, piece: Piece, color: Color, square: Square) -> usize {
+    let (side, square) = if perspective == Color::White {
+        (color != Color::White, square.to_index())
+    } else {
+        (color != Color::Black, square.to_index() ^ 56)
+    };
+
+    side as usize * 384 + piece.to_index() * 64 + square
+}
+
+impl Accumulator {
+    /// Build an accumulator from scratch
+    pub fn new(net: &Network, board: &Board) -> Self {
+        let mut acc = Self { vals: [net.feature_bias; 2] };
+
+        for square in *board.combined() {
+            if let (Some(piece), Some(color)) = (board.piece_on(square), board.color_on(square)) {
+                acc.apply(net, PieceChange { piece, color, square, add: true });
+            }
+        }
+
+        acc
+    }
+
+    /// Set the accumulator to `prev` with the changes of a move applied
+    pub fn update_from(&mut self, net: &Network, prev: &Self, changes: &[PieceChange]) {
+        self.vals = prev.vals;
+
+        for change in changes {
+            self.apply(net, *change);
+        }
+    }
+
+    #[inline]
+    fn apply(&mut self, net: &Network, change: PieceChange) {
+        for perspective in [Color::White, Color::Black] {
+            let weights = &net.feature_weights[feature(perspective, change.piece, change.color, change.square)];
+            let vals = &mut self.vals[perspective.to_index()];
+
+            if change.add {
+                vals.iter_mut().zip(weights.iter()).for_each(|(v, w)| *v += w);
+            } else {
+                vals.iter_mut().zip(weights.iter()).for_each(|(v, w)| *v -= w);
+            }
+        }
+    }
+}
+
+/// Get the network used by evaluation, if there is one. Searches take it once at the root, so
+/// replacing it only affects the following searches.
+pub fn network() -> Option<Arc<Network>> {
+    NETWORK.load_full()
+}
+
+/// Replace the network used by evaluation, or switch to the handcrafted evaluation with
+/// [`None`]
+pub fn set_network(net: Option<Network>) {
+    NETWORK.store(net.map(Arc::new));
+}
+
+/// [`set_network`] with a shared network, giving back the one it replaces so that it can be
+/// put back later
+pub fn replace_network(net: Option<Arc<Network>>) -> Option<Arc<Network>> {
+    NETWORK.swap(net)
+}
+
+/// The network built into the binary, which is `default.nnue` or the file at
+/// `DYSPROSIUM_EVALFILE` when it was built. Building fails if the file isn't a network.
+pub fn embedded_network() -> Option<Network> {
+    #[cfg(embedded_net)]
+    {
+        const EMBEDDED: &[u8] = include_bytes!(env!("DYSPROSIUM_EVALFILE"));
+        const _: () = assert!(
+            EMBEDDED.len() >= Network::SIZE && EMBEDDED.len() < Network::SIZE + 64,
+            "`DYSPROSIUM_EVALFILE` is not a network of the expected size",
+        );
+
+        Some(Network::from_bytes(EMBEDDED).expect("embedded network should be valid"))
+    }
+
+    #[cfg(not(embedded_net))]
+    None
+}
+
+/// Load the embedded network, if there is one and no network is loaded yet
+pub fn init() {
+    static INIT: std::sync::Once = std::sync::Once::new();
+
+    INIT.call_once(|| if network().is_none() {
+        if let Some(net) = embedded_network() {
+            set_network(Some(net));
+        }
+    });
+}
+
+#[test]
+fn test_accumulator() {
+    use crate::game::{piece_changes, replay_test_lines};
+
+    let mut rng = fastrand::Rng::with_seed(0xd15_b055);
+    let mut bytes = (0..Network::SIZE).map(|_| rng.u8(..)).collect::<Vec<_>>();
+    // keep the weights small enough to not overflow
+    for b in bytes.iter_mut().skip(1).step_by(2) {
+        *b = (*b as i8 >> 6) as u8;
+    }
+    let net = Network::from_bytes(&bytes).unwrap();
+    assert!(Network::from_bytes(&bytes[1..]).is_err());
+
+    replay_test_lines(|game| Accumulator::new(&net, game.board()), |acc, game, mov, next| {
+        let mut updated = acc.clone();
+        updated.update_from(&net, acc, &piece_changes(game.board(), mov));
+
+        let fresh = Accumulator::new(&net, next.board());
+        assert!(updated.vals == fresh.vals, "{mov} from {}", game.get_fen());
+        assert_eq!(net.evaluate(&updated, next.board().side_to_move()), net.evaluate(&fresh, next.board().side_to_move()));
+        *acc = updated;
+    });
+}
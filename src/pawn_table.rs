@@ -0,0 +1,36 @@
+use chess::BitBoard;
+use crate::eval::S;
+
+/// Per thread cache of the pawn structure evaluation, keyed by the pawn hash of the board
+pub struct PawnTable {
+    entries: Box<[PawnEntry]>,
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct PawnEntry {
+    key: u64,
+    /// Score of each color
+    pub score: [S; 2],
+    pub passed: BitBoard,
+}
+
+impl PawnTable {
+    const SIZE: usize = 1 << 14;
+
+    pub fn new() -> Self {
+        Self { entries: vec![PawnEntry::default(); Self::SIZE].into() }
+    }
+
+    /// Get the entry of the pawns, or fill it in with `f` which gets the score and passed pawns
+    #[inline]
+    pub fn get_or_insert_with<F: FnOnce() -> ([S; 2], BitBoard)>(&mut self, key: u64, f: F) -> PawnEntry {
+        let entry = &mut self.entries[key as usize % Self::SIZE];
+
+        if entry.key != key {
+            let (score, passed) = f();
+            *entry = PawnEntry { key, score, passed };
+        }
+
+        *entry
+    }
+}
@@ -0,0 +1,61 @@
+use crate::Game;
+use chess::{ChessMove, MoveGen};
+
+/// Count the leaf nodes of the legal move tree of `game` that is `depth` plies deep
+pub fn perft(game: &Game, depth: usize) -> usize {
+    if depth == 0 { return 1 };
+
+    let moves = MoveGen::new_legal(game.board());
+    if depth == 1 { return moves.len() };
+
+    moves.map(|m| perft(&game.make_move(m), depth - 1)).sum()
+}
+
+/// Same as [`perft`], but with the count split up by the moves at the root
+pub fn perft_divide(game: &Game, depth: usize) -> Vec<(ChessMove, usize)> {
+    MoveGen::new_legal(game.board())
+        .map(|m| (m, perft(&game.make_move(m), depth.saturating_sub(1))))
+        .collect()
+}
+
+#[test]
+fn test_perft() {
+    use core::str::FromStr;
+
+    const POSITIONS: &[(&str, usize, usize)] = &[
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 5, 4865609),
+        ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", 4, 4085603),
+        ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 5, 674624),
+        ("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", 4, 422333),
+        ("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", 4, 2103487),
+        ("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10", 4, 3894594),
+        // illegal en passant
+        ("3k4/3p4/8/K1P4r/8/8/8/8 b - - 0 1", 6, 1134888),
+        ("8/8/4k3/8/2p5/8/B2P2K1/8 w - - 0 1", 6, 1015133),
+        // en passant capture gives check
+        ("8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1", 6, 1440467),
+        // castling gives check
+        ("5k2/8/8/8/8/8/8/4K2R w K - 0 1", 6, 661072),
+        ("3k4/8/8/8/8/8/8/R3K3 w Q - 0 1", 6, 803711),
+        // castling rights
+        ("r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq - 0 1", 4, 1274206),
+        ("r3k2r/8/3Q4/8/8/5q2/8/R3K2R b KQkq - 0 1", 4, 1720476),
+        // promotions
+        ("2K2r2/4P3/8/8/8/8/8/3k4 w - - 0 1", 6, 3821001),
+        ("4k3/1P6/8/8/8/8/K7/8 w - - 0 1", 6, 217342),
+        ("8/P1k5/K7/8/8/8/8/8 w - - 0 1", 6, 92683),
+        // discovered check
+        ("8/8/1P2K3/8/2n5/1q6/8/5k2 b - - 0 1", 5, 1004658),
+        // stalemates and checkmates
+        ("K1k5/8/P7/8/8/8/8/8 w - - 0 1", 6, 2217),
+        ("8/k1P5/8/1K6/8/8/8/8 w - - 0 1", 7, 567584),
+        ("8/8/2k5/5q2/5n2/8/5K2/8 b - - 0 1", 4, 23527),
+    ];
+
+    for (fen, depth, nodes) in POSITIONS {
+        let game = Game::from_str(fen).unwrap();
+
+        assert_eq!(perft(&game, *depth), *nodes, "{fen}");
+        assert_eq!(perft_divide(&game, *depth).into_iter().map(|(_, n)| n).sum::<usize>(), *nodes, "{fen}");
+    }
+}
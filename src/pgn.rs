@@ -0,0 +1,457 @@
+use core::str::FromStr;
+use crate::{Eval, Game, game::FenError};
+use chess::{ChessMove, Color};
+
+/// A game score with its tags, moves, comments and variations
+#[derive(Clone)]
+pub struct Pgn {
+    pub tags: Vec<(String, String)>,
+    pub start: Game,
+    pub mainline: Variation,
+    pub result: GameResult,
+}
+
+/// A sequence of moves with the comment before the first one
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Variation {
+    pub comment: Option<String>,
+    pub moves: Vec<PgnMove>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgnMove {
+    pub mov: ChessMove,
+    /// Comment right after the move
+    pub comment: Option<String>,
+    /// Alternatives to this move, played from the position before it
+    pub variations: Vec<Variation>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    #[default]
+    Unknown,
+}
+
+impl Pgn {
+    /// Create an empty game from `start` with the seven tag roster filled with unknown values
+    pub fn new(start: Game) -> Self {
+        let mut tags = ["Event", "Site", "Date", "Round", "White", "Black"].into_iter()
+            .map(|name| (name.to_string(), "?".to_string()))
+            .collect::<Vec<_>>();
+        tags.push(("Result".to_string(), GameResult::Unknown.to_string()));
+
+        let fen = start.get_fen();
+        if fen != Game::default().get_fen() {
+            tags.push(("SetUp".to_string(), "1".to_string()));
+            tags.push(("FEN".to_string(), fen));
+        }
+
+        Self {
+            tags,
+            start,
+            mainline: Variation::default(),
+            result: GameResult::Unknown,
+        }
+    }
+
+    /// Parse the first game in `pgn`
+    pub fn parse(pgn: &str) -> Result<Self, PgnError> {
+        Parser { s: pgn, pos: 0 }.game()?.ok_or(PgnError::UnexpectedEnd)
+    }
+
+    /// Parse every game in `pgn`
+    pub fn parse_all(pgn: &str) -> Result<Vec<Self>, PgnError> {
+        let mut parser = Parser { s: pgn, pos: 0 };
+        let mut games = Vec::new();
+
+        while let Some(game) = parser.game()? {
+            games.push(game);
+        }
+
+        Ok(games)
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    pub fn set_tag(&mut self, name: &str, value: String) {
+        if let Some((_, v)) = self.tags.iter_mut().find(|(n, _)| n == name) {
+            *v = value;
+        } else {
+            self.tags.push((name.to_string(), value));
+        }
+    }
+
+    pub fn set_result(&mut self, result: GameResult) {
+        self.result = result;
+        self.set_tag("Result", result.to_string());
+    }
+
+    /// Add a move to the end of the main line
+    pub fn push(&mut self, mov: ChessMove, comment: Option<String>) {
+        self.mainline.moves.push(PgnMove { mov, comment, variations: Vec::new() });
+    }
+
+    /// Get the starting position followed by the position after each move of the main line
+    pub fn positions(&self) -> Vec<Game> {
+        let mut game = self.start.clone();
+        let mut positions = vec![game.clone()];
+
+        for m in self.mainline.moves.iter() {
+            game.play_move(m.mov);
+            positions.push(game.clone());
+        }
+
+        positions
+    }
+}
+
+/// Format an evaluation as a `{eval/depth}` move comment, e.g. `+0.35/12` or `-M3/20`
+pub fn eval_comment(eval: Eval, depth: usize) -> String {
+    if let Some(mate) = eval.mate_in() {
+        format!("{}M{}/{depth}", if mate >= 0 { '+' } else { '-' }, mate.abs())
+    } else {
+        format!("{:+.2}/{depth}", eval.0 as f32 / 100.0)
+    }
+}
+
+impl core::fmt::Display for Pgn {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for (name, value) in self.tags.iter() {
+            writeln!(f, "[{name} \"{}\"]", value.replace('\\', "\\\\").replace('"', "\\\""))?;
+        }
+        writeln!(f)?;
+
+        let mut tokens = Vec::new();
+        write_variation(&mut tokens, &self.start, &self.mainline);
+        tokens.push(self.result.to_string());
+
+        let mut line_len = 0;
+        for t in tokens {
+            if line_len != 0 && line_len + 1 + t.len() > 80 {
+                writeln!(f)?;
+                line_len = 0;
+            } else if line_len != 0 {
+                write!(f, " ")?;
+                line_len += 1;
+            }
+
+            write!(f, "{t}")?;
+            line_len += t.len();
+        }
+
+        writeln!(f)
+    }
+}
+
+fn write_variation(tokens: &mut Vec<String>, game: &Game, variation: &Variation) {
+    let mut game = game.clone();
+    let mut need_number = true;
+
+    if let Some(comment) = &variation.comment {
+        tokens.push(format!("{{{}}}", comment.replace('}', "")));
+    }
+
+    for m in variation.moves.iter() {
+        if game.board().side_to_move() == Color::White {
+            tokens.push(format!("{}.", game.fullmove_number()));
+        } else if need_number {
+            tokens.push(format!("{}...", game.fullmove_number()));
+        }
+        tokens.push(game.san(m.mov));
+        need_number = false;
+
+        if let Some(comment) = &m.comment {
+            tokens.push(format!("{{{}}}", comment.replace('}', "")));
+            need_number = true;
+        }
+
+        for v in m.variations.iter() {
+            let mut var_tokens = Vec::new();
+            write_variation(&mut var_tokens, &game, v);
+
+            if let Some(first) = var_tokens.first_mut() {
+                first.insert(0, '(');
+            }
+            if let Some(last) = var_tokens.last_mut() {
+                last.push(')');
+            }
+
+            tokens.extend(var_tokens);
+            need_number = true;
+        }
+
+        game = game.make_move(m.mov);
+    }
+}
+
+struct Parser<'a> {
+    s: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.s[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn take_while<F: Fn(char) -> bool>(&mut self, f: F) -> &'a str {
+        let start = self.pos;
+        while self.peek().is_some_and(&f) {
+            self.bump();
+        }
+
+        &self.s[start..self.pos]
+    }
+
+    fn skip_whitespace(&mut self) {
+        loop {
+            self.take_while(char::is_whitespace);
+
+            // `%` at the start of a line escapes the rest of it
+            if self.peek() == Some('%') && (self.pos == 0 || self.s[..self.pos].ends_with('\n')) {
+                self.take_while(|c| c != '\n');
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn game(&mut self) -> Result<Option<Pgn>, PgnError> {
+        self.skip_whitespace();
+        if self.peek().is_none() { return Ok(None) };
+
+        let mut tags = Vec::new();
+        while self.peek() == Some('[') {
+            tags.push(self.tag()?);
+            self.skip_whitespace();
+        }
+
+        let start = tags.iter()
+            .find(|(name, _)| name == "FEN")
+            .map_or(Ok(Game::default()), |(_, fen)| Game::from_str(fen))
+            .map_err(PgnError::InvalidFen)?;
+        let (mainline, result) = self.variation(&start, true)?;
+
+        let mut pgn = Pgn { tags, start, mainline, result: GameResult::Unknown };
+        pgn.result = result
+            .or_else(|| pgn.tag("Result").and_then(|r| r.parse().ok()))
+            .unwrap_or_default();
+
+        Ok(Some(pgn))
+    }
+
+    fn tag(&mut self) -> Result<(String, String), PgnError> {
+        self.bump();
+        self.skip_whitespace();
+        let name = self.take_while(|c| c.is_alphanumeric() || c == '_').to_string();
+        self.skip_whitespace();
+
+        if self.bump() != Some('"') {
+            return Err(PgnError::InvalidTag(name));
+        }
+
+        let mut value = String::new();
+        loop {
+            match self.bump() {
+                Some('\\') => value.extend(self.bump()),
+                Some('"') => break,
+                Some(c) => value.push(c),
+                None => return Err(PgnError::UnexpectedEnd),
+            }
+        }
+
+        self.skip_whitespace();
+        if self.bump() != Some(']') || name.is_empty() {
+            return Err(PgnError::InvalidTag(name));
+        }
+
+        Ok((name, value))
+    }
+
+    /// Parse moves played from `game` until the end of the game, or the end of the variation if
+    /// `main` is not set
+    fn variation(&mut self, game: &Game, main: bool) -> Result<(Variation, Option<GameResult>), PgnError> {
+        let mut variation = Variation::default();
+        let mut prev = game.clone();
+        let mut game = game.clone();
+
+        loop {
+            self.skip_whitespace();
+            let Some(c) = self.peek() else {
+                return if main { Ok((variation, None)) } else { Err(PgnError::UnexpectedEnd) };
+            };
+
+            match c {
+                '{' | ';' => {
+                    self.bump();
+                    let comment = if c == '{' {
+                        let comment = self.take_while(|c| c != '}');
+                        self.bump().ok_or(PgnError::UnexpectedEnd)?;
+                        comment
+                    } else {
+                        self.take_while(|c| c != '\n')
+                    };
+
+                    let to = variation.moves.last_mut().map_or(&mut variation.comment, |m| &mut m.comment);
+                    add_comment(to, comment);
+                },
+                '(' => {
+                    self.bump();
+                    let (v, _) = self.variation(&prev, false)?;
+                    variation.moves.last_mut()
+                        .ok_or_else(|| PgnError::UnexpectedToken("(".to_string()))?
+                        .variations.push(v);
+                },
+                ')' if !main => {
+                    self.bump();
+                    return Ok((variation, None));
+                },
+                '$' => {
+                    self.bump();
+                    self.take_while(|c| c.is_ascii_digit());
+                },
+                // the next game starts without a result
+                '[' if main => return Ok((variation, None)),
+                _ => {
+                    let token = self.take_while(|c| !c.is_whitespace() && !"{};()$[]".contains(c));
+                    if token.is_empty() {
+                        return Err(PgnError::UnexpectedToken(c.to_string()));
+                    }
+
+                    if let Ok(result) = token.parse::<GameResult>() {
+                        return if main { Ok((variation, Some(result))) } else { Err(PgnError::UnexpectedToken(token.to_string())) };
+                    }
+
+                    // move numbers can be stuck to the move like `1.e4`
+                    let san = token.rfind('.').map_or(token, |i| &token[i + 1..]);
+                    if san.is_empty() { continue };
+
+                    let mov = game.parse_san(san).ok_or_else(|| PgnError::IllegalMove(san.to_string()))?;
+                    let next = game.make_move(mov);
+                    prev = core::mem::replace(&mut game, next);
+
+                    variation.moves.push(PgnMove { mov, comment: None, variations: Vec::new() });
+                },
+            }
+        }
+    }
+}
+
+fn add_comment(to: &mut Option<String>, comment: &str) {
+    let comment = comment.trim();
+    if comment.is_empty() { return };
+
+    match to {
+        Some(c) => {
+            c.push(' ');
+            c.push_str(comment);
+        },
+        None => *to = Some(comment.to_string()),
+    }
+}
+
+impl FromStr for GameResult {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1-0" => Ok(Self::WhiteWins),
+            "0-1" => Ok(Self::BlackWins),
+            "1/2-1/2" => Ok(Self::Draw),
+            "*" => Ok(Self::Unknown),
+            _ => Err(()),
+        }
+    }
+}
+
+impl core::fmt::Display for GameResult {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            Self::WhiteWins => "1-0",
+            Self::BlackWins => "0-1",
+            Self::Draw => "1/2-1/2",
+            Self::Unknown => "*",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PgnError {
+    UnexpectedEnd,
+    UnexpectedToken(String),
+    InvalidTag(String),
+    InvalidFen(FenError),
+    IllegalMove(String),
+}
+
+impl core::fmt::Display for PgnError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UnexpectedEnd => write!(f, "PGN ended unexpectedly"),
+            Self::UnexpectedToken(token) => write!(f, "unexpected `{token}` in PGN"),
+            Self::InvalidTag(name) => write!(f, "tag `{name}` is malformed"),
+            Self::InvalidFen(err) => write!(f, "FEN tag is invalid: {err}"),
+            Self::IllegalMove(san) => write!(f, "`{san}` is not a legal move"),
+        }
+    }
+}
+
+impl core::error::Error for PgnError {}
+
+#[test]
+fn test_pgn() {
+    const PGN: &str = r#"[Event "Test"]
+[Site "?"]
+[White "A \"quoted\" name"]
+[Result "1-0"]
+
+1. e4 {best by test} e5 2. Nf3 (2. f4 exf4 (2... d5) 3. Nf3) 2... Nc6 $1 3.Bb5!? a6 ; line comment
+4. Ba4 Nf6 5. O-O Be7 1-0
+
+[Event "Second"]
+
+1. d4 *
+"#;
+
+    let games = Pgn::parse_all(PGN).unwrap();
+    assert_eq!(games.len(), 2);
+
+    let pgn = &games[0];
+    assert_eq!(pgn.tag("White"), Some("A \"quoted\" name"));
+    assert_eq!(pgn.result, GameResult::WhiteWins);
+    assert_eq!(pgn.mainline.moves.len(), 10);
+    assert_eq!(pgn.mainline.moves[0].comment.as_deref(), Some("best by test"));
+    assert_eq!(pgn.mainline.moves[2].variations.len(), 1);
+    assert_eq!(pgn.mainline.moves[2].variations[0].moves.len(), 3);
+    assert_eq!(pgn.mainline.moves[2].variations[0].moves[1].variations[0].moves.len(), 1);
+    assert_eq!(pgn.mainline.moves[5].comment.as_deref(), Some("line comment"));
+    assert_eq!(
+        pgn.positions().last().unwrap().get_fen(),
+        "r1bqk2r/1pppbppp/p1n2n2/4p3/B3P3/5N2/PPPP1PPP/RNBQ1RK1 w kq - 4 6",
+    );
+
+    assert_eq!(games[1].tag("Event"), Some("Second"));
+    assert_eq!(games[1].result, GameResult::Unknown);
+    assert_eq!(games[1].mainline.moves.len(), 1);
+
+    let written = pgn.to_string();
+    let reparsed = Pgn::parse(&written).unwrap();
+    assert_eq!(reparsed.tags, pgn.tags);
+    assert_eq!(reparsed.mainline, pgn.mainline);
+    assert_eq!(reparsed.result, pgn.result);
+
+    assert!(matches!(Pgn::parse("1. e4 e5 2. Ke3"), Err(PgnError::IllegalMove(_))));
+    assert!(matches!(Pgn::parse("1. e4 (1. d4"), Err(PgnError::UnexpectedEnd)));
+}
@@ -10,13 +10,17 @@ impl Engine {
         self.time_ref = Instant::now();
         self.total_nodes_searched.store(0, Ordering::Relaxed);
         self.debug.clear();
+        self.tb_hits.store(0, Ordering::Relaxed);
+        self.tb_root_move = self.probe_tb_root();
+        self.signals.begin();
 
         let mut main_thread = self.new_thread::<true>(0);
 
         let can_time_out = self.can_time_out.swap(false, Ordering::Relaxed);
-        let prev = main_thread.root_search(1, Eval::MIN, Eval::MAX);
+        let mut lines = main_thread.search_lines(1, &[]);
         self.can_time_out.store(can_time_out, Ordering::Relaxed);
-        let mut prev = (prev.0, prev.1, 1);
+        let mut prev = (lines[0].0, lines[0].1, 1);
+        *self.pv_lines.lock() = lines.clone();
         if !cont(self, prev) || self.soft_times_up() { return prev };
 
         *self.smp_prev.lock() = prev.1;
@@ -28,16 +32,17 @@ impl Engine {
         }
 
         for depth in 2..=255 {
-            let this = main_thread.root_aspiration(depth, prev.1);
+            let this = main_thread.search_lines(depth, &lines);
 
-            if self.hard_times_up() { break };
+            if main_thread.abort() { break };
 
-            prev = (this.0, this.1, depth);
-            if !cont(self, prev) || self.soft_times_up() { break };
+            lines = this;
+            prev = (lines[0].0, lines[0].1, depth);
+            *self.pv_lines.lock() = lines.clone();
+            if !cont(self, prev) || self.soft_times_up() || self.nodes() >= self.node_limit { break };
         }
 
         self.smp_abort.initiate();
-        println!("{:#?}", self.debug);
         prev
     }
 }
@@ -62,6 +67,34 @@ impl SmpThread<'_, false> {
     }
 }
 
+impl SmpThread<'_, true> {
+    /// Search for the best `multi_pv` root moves, each one with the ones before it excluded
+    fn search_lines(&mut self, depth: usize, prev: &[(ChessMove, Eval)]) -> Vec<(ChessMove, Eval)> {
+        let mut lines = Vec::with_capacity(self.multi_pv);
+        self.root_excluded.clear();
+
+        for i in 0..self.multi_pv {
+            let (mov, eval) = if let Some((_, prev)) = prev.get(i).or(prev.last()) {
+                self.root_aspiration(depth, *prev)
+            } else {
+                let (mov, eval, _) = self.root_search(depth, Eval::MIN, Eval::MAX);
+                (mov, eval)
+            };
+
+            // every legal move is already in a line
+            if i != 0 && mov == ChessMove::default() { break };
+            if self.abort() { break };
+
+            self.root_excluded.push(mov);
+            lines.push((mov, eval));
+        }
+
+        self.root_excluded.clear();
+        lines.sort_by_key(|(_, eval)| core::cmp::Reverse(*eval));
+        lines
+    }
+}
+
 impl<const MAIN: bool> SmpThread<'_, MAIN> {
     fn root_aspiration(&mut self, depth: usize, prev: Eval) -> (ChessMove, Eval) {
         let (alpha, beta) = (prev - 25, prev + 25);
@@ -83,10 +116,24 @@ impl<const MAIN: bool> SmpThread<'_, MAIN> {
         self.nodes_searched = 0;
 
         let game: Game = self.game.read().clone();
+        self.network = nnue::network();
+        if let Some(net) = self.network.as_deref() {
+            self.acc_stack.clear();
+            self.acc_stack.push(nnue::Accumulator::new(net, game.board()));
+        }
+        self.hash_stack.clear();
+        self.hash_stack.extend_from_slice(game.hash_history());
+        self.root_ply = self.hash_stack.len();
+        self.null_ply = None;
+
         let (next, eval, nt) = self._evaluate_search::<Pv, true>(ChessMove::default(), &game, &KillerTable::new(), depth, 0, alpha, beta, false);
 
-        self.store_tt(depth, &game, (next, eval, nt));
+        // the best move of the position isn't known if some moves are left out
+        if self.root_excluded.is_empty() {
+            self.store_tt(depth, &game, (next, eval, nt));
+        }
         self.total_nodes_searched.fetch_add(self.nodes_searched, Ordering::Relaxed);
+        self.nodes_searched = 0;
 
         (next, eval, nt)
     }
@@ -95,10 +142,15 @@ impl<const MAIN: bool> SmpThread<'_, MAIN> {
         if !MAIN {
             self.smp_abort.initiated()
         } else {
-            self.hard_times_up()
+            self.hard_times_up() || self.nodes_exhausted()
         }
     }
 
+    fn nodes_exhausted(&self) -> bool {
+        self.can_time_out.load(Ordering::Relaxed)
+            && self.total_nodes_searched.load(Ordering::Relaxed) + self.nodes_searched >= self.node_limit
+    }
+
     #[inline]
     fn zw_search<Node: node::Node>(
         &mut self,
@@ -132,6 +184,67 @@ impl<const MAIN: bool> SmpThread<'_, MAIN> {
         eval
     }
 
+    /// Put the position at `ply` onto the hash stack and check if it is a draw by repetition or
+    /// the fifty move rule
+    fn push_position(&mut self, game: &Game, ply: usize) -> bool {
+        let hash = game.board().get_hash();
+
+        self.hash_stack.truncate(self.root_ply + ply);
+        // positions before a null move can't be repeated as it isn't a real move
+        let reversible_plies = self.null_ply.map_or(game.fifty_move_counter(), |null| game.fifty_move_counter().min(ply - null));
+        let draw = game.fifty_move_counter() >= 100 || self.is_repetition(hash, reversible_plies);
+        self.hash_stack.push(hash);
+
+        draw
+    }
+
+    /// Check if the position with `hash` about to be pushed repeats. Repeating a position inside
+    /// the search tree once is treated as a draw, but positions from before the root have to
+    /// occur three times.
+    fn is_repetition(&self, hash: u64, reversible_plies: usize) -> bool {
+        let len = self.hash_stack.len();
+        let mut repeats = 0;
+
+        // anything before the last irreversible move can't repeat, and only positions with the
+        // same side to move can be equal
+        for dist in (2..=reversible_plies.min(len)).step_by(2) {
+            let idx = len - dist;
+
+            if self.hash_stack[idx] == hash {
+                if idx > self.root_ply { return true };
+
+                repeats += 1;
+                if repeats >= 2 { return true };
+            }
+        }
+
+        false
+    }
+
+    /// Set the accumulator at `ply` to the one before it with `mov` made from `parent`, where
+    /// [`None`] is a null move
+    fn push_accumulator(&mut self, parent: &Game, mov: Option<ChessMove>, ply: usize) {
+        let Some(net) = self.network.as_deref() else { return };
+
+        if self.acc_stack.len() <= ply {
+            self.acc_stack.resize(ply + 1, self.acc_stack[0].clone());
+        }
+
+        let (before, after) = self.acc_stack.split_at_mut(ply);
+        match mov {
+            Some(mov) => after[0].update_from(net, &before[ply - 1], &game::piece_changes(parent.board(), mov)),
+            None => after[0].clone_from(&before[ply - 1]),
+        }
+    }
+
+    /// Evaluate the position at `ply` with the network if there is one
+    fn evaluate_at(&mut self, game: &Game, ply: usize) -> Eval {
+        match self.network.as_deref() {
+            Some(net) => net.evaluate(&self.acc_stack[ply], game.board().side_to_move()),
+            None => evaluate_with(game, Some(&mut self.pawn_table)),
+        }
+    }
+
     fn store_tt(&self, depth: usize, game: &Game, (next, eval, nt): (ChessMove, Eval, NodeType)) {
         if nt != NodeType::None && !self.abort() {
             if let Some(tte) = self.trans_table.get_place(game.board().get_hash()) {
@@ -160,7 +273,7 @@ impl<const MAIN: bool> SmpThread<'_, MAIN> {
         beta: Eval,
         in_zw: bool,
     ) -> (ChessMove, Eval, NodeType) {
-        if game.can_declare_draw() {
+        if self.push_position(game, ply) && !ROOT {
             return (ChessMove::default(), Eval(0), NodeType::None);
         }
 
@@ -183,12 +296,20 @@ impl<const MAIN: bool> SmpThread<'_, MAIN> {
             BoardStatus::Stalemate => return (ChessMove::default(), Eval(0), NodeType::None),
         }
 
+        if !ROOT {
+            if let Some(wdl) = self.tablebase.as_ref().and_then(|tb| tb.probe_wdl(game)) {
+                self.tb_hits.fetch_add(1, Ordering::Relaxed);
+
+                return (ChessMove::default(), syzygy::wdl_eval(wdl, ply), NodeType::None);
+            }
+        }
+
         if self.abort() {
             return (ChessMove::default(), Eval(0), NodeType::None);
         }
 
         if depth == 0 {
-            return (ChessMove::default(), self.quiescence_search(game, alpha, beta), NodeType::None);
+            return (ChessMove::default(), self.quiescence_search(game, ply, alpha, beta), NodeType::None);
         }
 
         let killer = KillerTable::new();
@@ -212,9 +333,13 @@ impl<const MAIN: bool> SmpThread<'_, MAIN> {
             game.board().pieces(Piece::Rook).0 != 0 ||
             game.board().pieces(Piece::Queen).0 != 0
         ) {
-            let game = game.make_null_move().unwrap();
+            let parent = game;
+            let game = parent.make_null_move().unwrap();
+            self.push_accumulator(parent, None, ply + 1);
             let r = if depth > 7 && game.board().color_combined(game.board().side_to_move()).popcnt() >= 2 { 5 } else { 4 };
+            let null_ply = self.null_ply.replace(ply + 1);
             let eval = -self.zw_search::<Cut>(prev_move, &game, &killer, depth - r, ply + 1, 1 - beta);
+            self.null_ply = null_ply;
 
             if eval >= beta {
                 return (ChessMove::default(), eval.incr_mate(), NodeType::None);
@@ -224,10 +349,15 @@ impl<const MAIN: bool> SmpThread<'_, MAIN> {
         let tte = self.trans_table.get(game.board().get_hash());
 
         let mut moves = MoveGen::new_legal(game.board())
+            .filter(|m| !ROOT || (
+                !self.root_excluded.contains(m)
+                && (self.root_moves.is_empty() || self.root_moves.contains(m))
+                && self.tb_root_move.is_none_or(|tb| tb == *m)
+            ))
             .map(|m| (m, self.move_score(m, prev_move, game, &tte, &p_killer)))
             .collect::<arrayvec::ArrayVec<_, 256>>();
         moves.sort_unstable_by_key(|i| -i.1);
-        if ROOT && !MAIN {
+        if ROOT && !MAIN && !moves.is_empty() {
             let len = moves.len();
             moves.rotate_left((self.index / 2) % len);
         }
@@ -237,10 +367,11 @@ impl<const MAIN: bool> SmpThread<'_, MAIN> {
         let _game = &game;
         for (i, (m, _)) in moves.iter().copied().enumerate() {
             let game = _game.make_move(m);
+            self.push_accumulator(_game, Some(m), ply + 1);
 
             // futility pruning: kill nodes with no potential
             if !in_check && depth <= 2 {
-                let eval = -evaluate_static(game.board());
+                let eval = -self.evaluate_at(&game, ply + 1);
                 let margin = 100 * depth as i16 * depth as i16;
 
                 if eval.0 + margin < alpha.0 {
@@ -319,8 +450,8 @@ impl<const MAIN: bool> SmpThread<'_, MAIN> {
         (best.0, best.1.incr_mate(), if best.1 == alpha { NodeType::All } else { NodeType::Pv })
     }
 
-    fn quiescence_search(&mut self, game: &Game, mut alpha: Eval, beta: Eval) -> Eval {
-        let standing_pat = evaluate_static(game.board());
+    fn quiescence_search(&mut self, game: &Game, ply: usize, mut alpha: Eval, beta: Eval) -> Eval {
+        let standing_pat = self.evaluate_at(game, ply);
         // TODO: failing to standing pat makes sprt fail, need investigation
         if standing_pat >= beta { return beta; }
         alpha = alpha.max(standing_pat);
@@ -332,8 +463,10 @@ impl<const MAIN: bool> SmpThread<'_, MAIN> {
         for m in moves {
             if see(game, m) < 0 { continue };
 
-            let game = game.make_move(m);
-            let eval = -self.quiescence_search(&game, -beta, -alpha);
+            let parent = game;
+            let game = parent.make_move(m);
+            self.push_accumulator(parent, Some(m), ply + 1);
+            let eval = -self.quiescence_search(&game, ply + 1, -beta, -alpha);
             self.nodes_searched += 1;
 
             if eval > best {
@@ -26,6 +26,15 @@ impl<T: Default + Clone + Sized + Send + Sync + NoUninit> SharedHashTable<T> {
         Self { inner: inner.into() }
     }
 
+    /// [`new`](Self::new) that fails instead of aborting when there isn't enough memory
+    pub fn try_new(size: usize) -> Result<Self, std::collections::TryReserveError> {
+        let mut inner = Vec::new();
+        inner.try_reserve_exact(size)?;
+        inner.resize_with(size, UnsafeCell::default);
+
+        Ok(Self { inner: inner.into() })
+    }
+
     pub fn clear(&mut self) {
         for e in self.inner.iter_mut() {
             unsafe { (*e.get()).hash = 0 };
@@ -0,0 +1,207 @@
+use chess::{BitBoard, Board, ChessMove, Color, Piece, Square};
+use pyrrhic_rs::{DtzProbeValue, EngineAdapter, TableBases};
+use crate::{Eval, Game};
+
+pub use pyrrhic_rs::{TBError, WdlProbeResult as Wdl};
+
+/// Syzygy endgame tablebases
+pub struct Tablebase {
+    tb: TableBases<Adapter>,
+}
+
+#[derive(Clone)]
+struct Adapter;
+
+impl EngineAdapter for Adapter {
+    fn pawn_attacks(color: pyrrhic_rs::Color, square: u64) -> u64 {
+        let color = match color {
+            pyrrhic_rs::Color::White => Color::White,
+            pyrrhic_rs::Color::Black => Color::Black,
+        };
+
+        chess::get_pawn_attacks(Square::new(square as u8), color, BitBoard(!0)).0
+    }
+
+    fn knight_attacks(square: u64) -> u64 {
+        chess::get_knight_moves(Square::new(square as u8)).0
+    }
+
+    fn bishop_attacks(square: u64, occupied: u64) -> u64 {
+        chess::get_bishop_moves(Square::new(square as u8), BitBoard(occupied)).0
+    }
+
+    fn rook_attacks(square: u64, occupied: u64) -> u64 {
+        chess::get_rook_moves(Square::new(square as u8), BitBoard(occupied)).0
+    }
+
+    fn queen_attacks(square: u64, occupied: u64) -> u64 {
+        Self::bishop_attacks(square, occupied) | Self::rook_attacks(square, occupied)
+    }
+
+    fn king_attacks(square: u64) -> u64 {
+        chess::get_king_moves(Square::new(square as u8)).0
+    }
+}
+
+/// The arguments every probe takes to describe a position
+struct ProbePosition {
+    white: u64,
+    black: u64,
+    kings: u64,
+    queens: u64,
+    rooks: u64,
+    bishops: u64,
+    knights: u64,
+    pawns: u64,
+    ep: u32,
+    turn: bool,
+}
+
+impl ProbePosition {
+    fn new(board: &Board) -> Self {
+        let pieces = |piece| board.pieces(piece).0;
+
+        Self {
+            white: board.color_combined(Color::White).0,
+            black: board.color_combined(Color::Black).0,
+            kings: pieces(Piece::King),
+            queens: pieces(Piece::Queen),
+            rooks: pieces(Piece::Rook),
+            bishops: pieces(Piece::Bishop),
+            knights: pieces(Piece::Knight),
+            pawns: pieces(Piece::Pawn),
+            // the board keeps the square of the pawn that can be captured, the tablebases want
+            // the square behind it
+            ep: board.en_passant().map_or(0, |sq| sq.uforward(board.side_to_move()).to_index() as u32),
+            turn: board.side_to_move() == Color::White,
+        }
+    }
+}
+
+impl Tablebase {
+    /// Load the tablebases from the directories in `path`, separated by `:` (or `;` on Windows)
+    pub fn open(path: &str) -> Result<Self, TBError> {
+        Ok(Self { tb: TableBases::new(path)? })
+    }
+
+    /// The largest amount of pieces the loaded tablebases have
+    pub fn max_pieces(&self) -> u32 { self.tb.max_pieces() }
+
+    /// Tablebases don't have positions with castling rights or more pieces than they cover
+    fn can_probe(&self, board: &Board) -> bool {
+        board.combined().popcnt() <= self.max_pieces()
+            && board.castle_rights(Color::White) == chess::CastleRights::NoRights
+            && board.castle_rights(Color::Black) == chess::CastleRights::NoRights
+    }
+
+    /// Probe the win/draw/loss of a position. This only probes right after a capture or pawn
+    /// move, as the result is only exact when the fifty move counter is reset.
+    pub fn probe_wdl(&self, game: &Game) -> Option<Wdl> {
+        let board = game.board();
+        if game.fifty_move_counter() != 0 || !self.can_probe(board) {
+            return None;
+        }
+
+        let p = ProbePosition::new(board);
+        self.tb.probe_wdl(p.white, p.black, p.kings, p.queens, p.rooks, p.bishops, p.knights, p.pawns, p.ep, p.turn).ok()
+    }
+
+    /// Find the move that keeps the best win/draw/loss while making progress under the fifty
+    /// move rule
+    pub fn probe_root(&self, game: &Game) -> Option<(ChessMove, Wdl)> {
+        let board = game.board();
+        if !self.can_probe(board) {
+            return None;
+        }
+
+        let p = ProbePosition::new(board);
+        let result = self.tb.probe_root(
+            p.white, p.black, p.kings, p.queens, p.rooks, p.bishops, p.knights, p.pawns,
+            game.fifty_move_counter() as u32,
+            p.ep,
+            p.turn,
+        ).ok()?;
+
+        match result.root {
+            DtzProbeValue::DtzResult(r) => {
+                let promotion = match r.promotion {
+                    pyrrhic_rs::Piece::Queen => Some(Piece::Queen),
+                    pyrrhic_rs::Piece::Rook => Some(Piece::Rook),
+                    pyrrhic_rs::Piece::Bishop => Some(Piece::Bishop),
+                    pyrrhic_rs::Piece::Knight => Some(Piece::Knight),
+                    _ => None,
+                };
+                let mov = ChessMove::new(Square::new(r.from_square), Square::new(r.to_square), promotion);
+
+                // don't trust the tablebases with a move the board doesn't agree with
+                chess::MoveGen::new_legal(board).any(|m| m == mov).then_some((mov, r.wdl))
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Score of a tablebase result `ply` plies from the root from the point of view of the side to
+/// move, preferring shorter wins
+pub fn wdl_eval(wdl: Wdl, ply: usize) -> Eval {
+    match wdl {
+        Wdl::Win => Eval::TB_WIN - ply as i16,
+        Wdl::Loss => -(Eval::TB_WIN - ply as i16),
+        // cursed wins and blessed losses are draws under the fifty move rule
+        _ => Eval(0),
+    }
+}
+
+/// Probing needs the tablebase files, which aren't kept in the repository. The test only uses
+/// the KQvK, KNvK and KPvK tables (both `.rtbw` and `.rtbz`), run it with
+///
+/// ```text
+/// SYZYGY_PATH=/path/to/syzygy cargo test test_syzygy -- --ignored
+/// ```
+#[test]
+#[ignore = "needs the 3 piece Syzygy tables in `SYZYGY_PATH`"]
+fn test_syzygy() {
+    use core::str::FromStr;
+
+    let path = std::env::var("SYZYGY_PATH").expect("`SYZYGY_PATH` should point to the Syzygy tables");
+    let tb = Tablebase::open(&path).unwrap();
+    assert!(tb.max_pieces() >= 3);
+
+    const WDL: &[(&str, Wdl)] = &[
+        ("4k3/8/8/8/8/8/8/3QK3 w - - 0 1", Wdl::Win),
+        ("4k3/8/8/8/8/8/8/3QK3 b - - 0 1", Wdl::Loss),
+        ("4k3/8/8/8/8/8/8/3NK3 w - - 0 1", Wdl::Draw),
+        ("8/8/8/8/8/2k5/2p5/4K3 b - - 0 1", Wdl::Win),
+    ];
+
+    for (fen, wdl) in WDL {
+        let game = Game::from_str(fen).unwrap();
+        assert_eq!(tb.probe_wdl(&game), Some(*wdl), "{fen}");
+    }
+
+    // castling rights and a non zero fifty move counter can't be probed
+    assert_eq!(tb.probe_wdl(&Game::from_str("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap()), None);
+    assert_eq!(tb.probe_wdl(&Game::from_str("4k3/8/8/8/8/8/8/3QK3 w - - 5 10").unwrap()), None);
+
+    let game = Game::from_str("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+    let (_, wdl) = tb.probe_root(&game).unwrap();
+    assert_eq!(wdl, Wdl::Win);
+}
+
+#[test]
+fn test_wdl_eval() {
+    use core::str::FromStr;
+
+    assert!(wdl_eval(Wdl::Win, 3) > wdl_eval(Wdl::Win, 5));
+    assert!(wdl_eval(Wdl::Win, 5) > Eval(0));
+    assert_eq!(wdl_eval(Wdl::Loss, 4), -wdl_eval(Wdl::Win, 4));
+    for wdl in [Wdl::CursedWin, Wdl::Draw, Wdl::BlessedLoss] {
+        assert_eq!(wdl_eval(wdl, 2), Eval(0));
+    }
+
+    // the tablebases want the square behind the pawn that can be captured en passant
+    let game = Game::from_str("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3").unwrap();
+    let p = ProbePosition::new(game.board());
+    assert_eq!((p.ep, p.turn), (Square::F6.to_index() as u32, true));
+    assert_eq!(p.kings, BitBoard::from_square(Square::E1).0 | BitBoard::from_square(Square::E8).0);
+}